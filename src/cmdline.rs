@@ -0,0 +1,223 @@
+use std::{cmp::min, path::PathBuf};
+
+use crate::{
+    format::ExportFormat,
+    keymap::{self, NamedAction},
+    message::{BulkMsg, Message, NormalMsg, SaveMsg, SelectMsg, SelectedMsg},
+    model::{Mode, SaveState, SessionState},
+};
+
+// Built-in commands with their usage, taking precedence over action names.
+const COMMANDS: [(&str, &str); 7] = [
+    ("export", "export md|txt <path>"),
+    ("goto", "goto <index>"),
+    ("q", "q"),
+    ("rename", "rename <filename>"),
+    ("sort", "sort"),
+    ("w", "w [filename]"),
+    ("wq", "wq"),
+];
+
+/// An IO action or message resulting from a command line.
+pub enum Action {
+    Message(Message),
+    Export(ExportFormat, PathBuf),
+    Rename(String),
+    SaveNew(String),
+}
+
+// Error for commands acting on an item when there is none.
+const NO_ITEMS: &str = "No items";
+
+// Return the cursor index, or an error if the forest is empty.
+fn cursor(state: &SessionState) -> Result<usize, String> {
+    match state.root.size() {
+        0 => Err(NO_ITEMS.to_string()),
+        _ => Ok(state.cursor),
+    }
+}
+
+// Return the `path` with a leading `~` replaced by the home directory.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+// Return the message sending the action called `name`, acting on the cursor.
+fn action(name: &str, state: &SessionState) -> Result<Message, String> {
+    let message = match keymap::named_action(name) {
+        Some(NamedAction::Normal(msg)) => Message::Normal(msg),
+        Some(NamedAction::Select(msg)) => Message::Select(msg, cursor(state)?),
+        Some(NamedAction::Selected(msg)) => {
+            Message::Selected(msg, cursor(state)?)
+        }
+        None => return Err(format!("Unknown command: {name}")),
+    };
+    Ok(message)
+}
+
+/// Parse the command line `text` into the Action it performs on the session
+/// `state`.
+///
+/// Besides the built-in commands, the name of any action in Normal, Selected
+/// or Select mode runs it on the item at the cursor.
+/// Returns a description of the problem if the command is invalid.
+pub fn parse(text: &str, state: &SessionState) -> Result<Action, String> {
+    let text = text.trim();
+    let (name, args) = match text.split_once(char::is_whitespace) {
+        Some((name, args)) => (name, args.trim_start()),
+        None => (text, ""),
+    };
+    let is_open = state.maybe_file.is_some();
+    let message = match (name, args) {
+        ("export", args) => {
+            let (format, path) = args.split_once(char::is_whitespace)
+                .ok_or("Usage: export md|txt <path>")?;
+            let format = match format {
+                "md" => ExportFormat::Markdown,
+                "txt" => ExportFormat::Text,
+                _ => return Err(format!("Unknown export format: {format}")),
+            };
+            return Ok(Action::Export(format, expand_home(path.trim())));
+        }
+        ("goto", index) if !index.is_empty() => {
+            let index: usize = index.parse()
+                .map_err(|_| format!("Invalid index: {index}"))?;
+            match state.root.size() {
+                0 => return Err(NO_ITEMS.to_string()),
+                size => Message::Continue(Mode::Select(min(index, size - 1))),
+            }
+        }
+        ("q", "") => Message::Normal(NormalMsg::Quit),
+        ("rename", filename) if !filename.is_empty() => match is_open {
+            true => return Ok(Action::Rename(filename.to_string())),
+            false => return Err("No open file, use w to save".to_string()),
+        }
+        ("sort", "") => match state.root.mark_count() {
+            0 => Message::Selected(SelectedMsg::Sort, cursor(state)?),
+            _ => {
+                let msg = SelectMsg::Bulk(BulkMsg::Sort);
+                Message::Select(msg, cursor(state)?)
+            }
+        }
+        ("w", "") => Message::Save(SaveMsg::Confirm, SaveState::new_continue()),
+        ("w", filename) => match is_open {
+            true => return Err("File already saved, use rename".to_string()),
+            false => return Ok(Action::SaveNew(filename.to_string())),
+        }
+        ("wq", "") => Message::Save(SaveMsg::Confirm, SaveState::new_quit()),
+        (name, args) => {
+            let command = COMMANDS.iter().find(|(command, _)| *command == name);
+            if let Some((_, usage)) = command {
+                return Err(format!("Usage: {usage}"));
+            }
+            let message = action(name, state)?;
+            match args.is_empty() {
+                true => message,
+                false => return Err(format!("Unexpected arguments: {args}")),
+            }
+        }
+    };
+    Ok(Action::Message(message))
+}
+
+/// Complete the command name typed on the command line `text`.
+///
+/// Returns the completed text, with a message listing the matching names if
+/// there are several, or stating that there are none.
+pub fn complete(text: &str) -> (String, Option<String>) {
+    if text.contains(char::is_whitespace) {
+        return (text.to_string(), None);
+    }
+    let mut names: Vec<&str> = COMMANDS.iter()
+        .map(|(name, _)| *name)
+        .chain(keymap::action_names())
+        .filter(|name| name.starts_with(text))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    match names.as_slice() {
+        [] => (text.to_string(), Some("No matching command".to_string())),
+        [name] => (format!("{name} "), None),
+        [first, rest @ ..] => {
+            let common = rest.iter().fold(first.len(), |len, name| {
+                first.bytes()
+                    .zip(name.bytes())
+                    .take(len)
+                    .take_while(|(a, b)| a == b)
+                    .count()
+            });
+            (first[..common].to_string(), Some(names.join(" ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Return an unsaved session state with root items labeled `labels` and
+    // the cursor on the second.
+    fn session(labels: &[&str]) -> SessionState {
+        SessionState { cursor: 1, ..SessionState::with_labels(labels) }
+    }
+
+    #[test]
+    fn parse_commands() {
+        let state = session(&["a", "b", "c"]);
+        let parse = |text| parse(text, &state);
+        let error = |text| parse(text).err().unwrap_or_default();
+
+        assert!(matches!(
+            parse("goto 9"),
+            Ok(Action::Message(Message::Continue(Mode::Select(2)))),
+        ));
+        assert!(matches!(
+            parse(" export md  out file.md"),
+            Ok(Action::Export(ExportFormat::Markdown, path))
+                if path.as_os_str() == "out file.md",
+        ));
+        assert!(matches!(
+            parse("sort"),
+            Ok(Action::Message(Message::Selected(SelectedMsg::Sort, 1))),
+        ));
+        assert!(matches!(
+            parse("delete_tree"),
+            Ok(Action::Message(Message::Selected(SelectedMsg::DeleteTree, 1))),
+        ));
+        assert!(matches!(
+            parse("w notes"),
+            Ok(Action::SaveNew(filename)) if filename == "notes",
+        ));
+        assert_eq!(error("goto x"), "Invalid index: x");
+        assert_eq!(error("export pdf out.pdf"), "Unknown export format: pdf");
+        assert_eq!(error("rename notes"), "No open file, use w to save");
+        assert_eq!(error("goto"), "Usage: goto <index>");
+        assert_eq!(error("edit now"), "Unexpected arguments: now");
+        assert_eq!(error("frobnicate"), "Unknown command: frobnicate");
+        assert_eq!(
+            super::parse("edit", &session(&[])).err().unwrap_or_default(),
+            NO_ITEMS,
+        );
+    }
+
+    #[test]
+    fn complete_command_names() {
+        assert_eq!(complete("go"), ("goto ".to_string(), None));
+        assert_eq!(complete("goto 3"), ("goto 3".to_string(), None));
+        assert_eq!(
+            complete("paste_"),
+            (
+                "paste_".to_string(),
+                Some("paste_after paste_before paste_child".to_string()),
+            ),
+        );
+        assert_eq!(complete("dup").0, "duplicate");
+        assert_eq!(
+            complete("xyz"),
+            ("xyz".to_string(), Some("No matching command".to_string())),
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::{collections::BTreeMap, fs, io::ErrorKind};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    keymap::{KeyBinding, Keymap, Keys},
+    storage::{FsStorage, Storage},
+    theme::{self, ColorSupport, Palette, Theme},
+};
+
+// Name of the theme used when none is chosen.
+const DEFAULT_THEME: &str = "dark";
+// Contents of the config file.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    keys: BTreeMap<String, BTreeMap<String, Keys>>,
+    theme: Option<String>,
+    themes: BTreeMap<String, Palette>,
+}
+
+// Contents of the macros file: the keys of each macro by register name.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct MacroFile {
+    macros: BTreeMap<String, Vec<String>>,
+}
+
+// Read the saved macros from the `storage`, which are empty if there are none.
+fn read_macros(storage: &dyn Storage) -> Result<MacroFile, String> {
+    let text = storage.read_macros()
+        .map_err(|error| format!("Failed to read macros: {error}"))?;
+    match text {
+        Some(text) => toml::from_str(&text).map_err(|error| {
+            format!("Invalid macros file: {error}")
+        }),
+        None => Ok(MacroFile::default()),
+    }
+}
+
+/// Return the keys of the macro saved in the `register` of the `storage`.
+///
+/// Returns a description of the problem if there is no such macro or it
+/// cannot be read.
+pub fn load_macro(
+    register: char,
+    storage: &dyn Storage,
+) -> Result<Vec<KeyBinding>, String> {
+    let keys = read_macros(storage)?
+        .macros
+        .remove(&register.to_string())
+        .ok_or_else(|| format!("No macro in register {register}"))?;
+    keys.iter()
+        .map(|key| {
+            KeyBinding::parse(key).ok_or_else(|| {
+                format!("macros.{register}: invalid key \"{key}\"")
+            })
+        })
+        .collect()
+}
+
+/// Save the macro `keys` in the `register` of the `storage`, replacing any
+/// macro saved there.
+///
+/// Returns a description of the problem if a key cannot be written in the
+/// file or the file cannot be written.
+pub fn save_macro(
+    register: char,
+    keys: &[KeyBinding],
+    storage: &dyn Storage,
+) -> Result<(), String> {
+    let mut file = read_macros(storage)?;
+    let keys = keys.iter()
+        .map(|key| key.name().ok_or_else(|| format!("Cannot save key {key}")))
+        .collect::<Result<_, _>>()?;
+    file.macros.insert(register.to_string(), keys);
+    let text = toml::to_string(&file).map_err(|error| error.to_string())?;
+    storage.write_macros(&text)
+        .map_err(|error| format!("Failed to save macros: {error}"))
+}
+
+/// User settings, read from the config file.
+pub struct Config {
+    pub keymap: Keymap,
+    pub theme: Theme,
+}
+
+impl Config {
+    /// Load the config file from the config directory of the `storage`, using
+    /// the defaults if there is none.
+    ///
+    /// Returns a message describing every problem found in the file on
+    /// failure.
+    pub fn load(storage: &FsStorage) -> Result<Self, String> {
+        let support = ColorSupport::detect();
+        let Some(path) = storage.config_path() else {
+            return Config::parse("", support);
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == ErrorKind::NotFound => String::new(),
+            Err(error) => {
+                return Err(format!("{}: {error}", path.display()));
+            }
+        };
+        Config::parse(&text, support).map_err(|error| {
+            format!("Invalid config file {}:\n{error}", path.display())
+        })
+    }
+
+    // Parse the `text` of a config file, converting the theme colors to the
+    // color `support` of the terminal.
+    fn parse(text: &str, support: ColorSupport) -> Result<Self, String> {
+        let ConfigFile { keys, theme, themes } = toml::from_str(text)
+            .map_err(|error| error.to_string())?;
+        let mut errors = Vec::new();
+        let keymap = Keymap::new(&keys)
+            .unwrap_or_else(|keymap_errors| {
+                errors.extend(keymap_errors);
+                Keymap::default()
+            });
+        let name = theme.as_deref().unwrap_or(DEFAULT_THEME);
+        let palette = themes.get(name)
+            .copied()
+            .or_else(|| theme::builtin_palette(name))
+            .unwrap_or_else(|| {
+                errors.push(format!(
+                    "theme: unknown theme \"{name}\", expected one of {} or \
+                     a theme defined in [themes]",
+                    theme::THEME_NAMES.join(", "),
+                ));
+                theme::builtin_palette(DEFAULT_THEME)
+                    .expect("The default theme is built in")
+            });
+        match errors.is_empty() {
+            true => Ok(Config { keymap, theme: Theme::new(palette, support) }),
+            false => Err(errors.join("\n")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::style::{Color, Style};
+
+    use crate::storage::MemoryStorage;
+
+    use super::*;
+
+    #[test]
+    fn parse_themes() {
+        let parse = |text| Config::parse(text, ColorSupport::TrueColor);
+        let custom = "theme = 'mine'\n\
+            [themes.mine]\n\
+            bg = 'black'\nbg_dark = 'black'\nbg2 = 'blue'\n\
+            fg = '#ffffff'\nfg_dark = 'yellow'\ngray = '8'";
+        let default = Style::new()
+            .fg(Color::Rgb(255, 255, 255))
+            .bg(Color::Black);
+
+        assert_eq!(parse(custom).unwrap().theme.default, default);
+        assert!(parse("theme = 'light'").is_ok());
+        assert!(parse("theme = 'unknown'").is_err());
+        assert!(parse("[themes.mine]\nbg = 'black'").is_err());
+    }
+
+    #[test]
+    fn save_and_load_macros() {
+        let storage = MemoryStorage::new();
+        let keys: Vec<KeyBinding> = ["j", "ctrl+w", "shift+tab"]
+            .iter()
+            .filter_map(|key| KeyBinding::parse(key))
+            .collect();
+        let insert = KeyEvent::new(KeyCode::Insert, KeyModifiers::NONE);
+
+        save_macro('a', &keys, &storage).unwrap();
+        assert_eq!(load_macro('a', &storage).unwrap(), keys);
+        assert_eq!(
+            save_macro('b', &[KeyBinding::from(insert)], &storage),
+            Err("Cannot save key Insert".to_string()),
+        );
+        assert!(load_macro('b', &storage).is_err());
+    }
+}
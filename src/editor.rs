@@ -0,0 +1,216 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A single line of editable text with a cursor.
+///
+/// The `cursor` is a byte offset into the `text`, always on a grapheme
+/// boundary.
+#[derive(Default)]
+pub struct LineEditor {
+    text: String,
+    cursor: usize,
+}
+
+impl LineEditor {
+    /// Create a LineEditor containing the `text`, with the cursor at the end.
+    pub fn new(text: String) -> Self {
+        let cursor = text.len();
+        LineEditor { text, cursor }
+    }
+
+    /// Return a reference to the text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Consume the LineEditor, returning its text.
+    pub fn into_text(self) -> String {
+        self.text
+    }
+
+    /// Return whether the text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Return the byte offset of the cursor in the text.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    // Return the byte offset of the grapheme boundary before the cursor.
+    fn prev_boundary(&self) -> usize {
+        self.text[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    // Return the byte offset of the grapheme boundary after the cursor.
+    fn next_boundary(&self) -> usize {
+        self.text[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |g| self.cursor + g.len())
+    }
+
+    // Return the byte offset of the start of the word before the cursor,
+    // skipping any whitespace first.
+    fn prev_word(&self) -> usize {
+        let mut in_word = false;
+        for (i, g) in self.text[..self.cursor].grapheme_indices(true).rev() {
+            let is_space = g.trim().is_empty();
+            if in_word && is_space {
+                return i + g.len();
+            }
+            in_word |= !is_space;
+        }
+        0
+    }
+
+    // Return the byte offset of the end of the word after the cursor,
+    // skipping any whitespace first.
+    fn next_word(&self) -> usize {
+        let mut in_word = false;
+        for (i, g) in self.text[self.cursor..].grapheme_indices(true) {
+            let is_space = g.trim().is_empty();
+            if in_word && is_space {
+                return self.cursor + i;
+            }
+            in_word |= !is_space;
+        }
+        self.text.len()
+    }
+
+    /// Insert the character `c` at the cursor, moving the cursor after it.
+    pub fn insert(mut self, c: char) -> Self {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+        self
+    }
+
+    /// Insert the string `s` at the cursor, moving the cursor after it.
+    pub fn insert_str(mut self, s: &str) -> Self {
+        self.text.insert_str(self.cursor, s);
+        self.cursor += s.len();
+        self
+    }
+
+    /// Delete the grapheme before the cursor.
+    pub fn backspace(mut self) -> Self {
+        let start = self.prev_boundary();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+        self
+    }
+
+    /// Delete the grapheme at the cursor.
+    pub fn delete(mut self) -> Self {
+        let end = self.next_boundary();
+        self.text.replace_range(self.cursor..end, "");
+        self
+    }
+
+    /// Move the cursor one grapheme to the left.
+    pub fn left(mut self) -> Self {
+        self.cursor = self.prev_boundary();
+        self
+    }
+
+    /// Move the cursor one grapheme to the right.
+    pub fn right(mut self) -> Self {
+        self.cursor = self.next_boundary();
+        self
+    }
+
+    /// Move the cursor to the start of the previous word.
+    pub fn word_left(mut self) -> Self {
+        self.cursor = self.prev_word();
+        self
+    }
+
+    /// Move the cursor to the end of the next word.
+    pub fn word_right(mut self) -> Self {
+        self.cursor = self.next_word();
+        self
+    }
+
+    /// Move the cursor to the start of the text.
+    pub fn home(mut self) -> Self {
+        self.cursor = 0;
+        self
+    }
+
+    /// Move the cursor to the end of the text.
+    pub fn end(mut self) -> Self {
+        self.cursor = self.text.len();
+        self
+    }
+
+    /// Delete the word before the cursor.
+    pub fn kill_word(mut self) -> Self {
+        let start = self.prev_word();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+        self
+    }
+
+    /// Delete the text before the cursor.
+    pub fn kill_to_start(mut self) -> Self {
+        self.text.replace_range(..self.cursor, "");
+        self.cursor = 0;
+        self
+    }
+
+    /// Delete the text from the cursor to the end.
+    pub fn kill_to_end(mut self) -> Self {
+        self.text.truncate(self.cursor);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_at_cursor() {
+        let editor = LineEditor::new("helo world".to_string())
+            .word_left()
+            .left()
+            .left()
+            .insert('l');
+
+        assert_eq!(editor.text(), "hello world");
+        assert_eq!(editor.cursor(), 4);
+        let editor = editor.home().delete().end().backspace();
+        assert_eq!(editor.text(), "ello worl");
+    }
+
+    #[test]
+    fn move_and_kill_words() {
+        let editor = LineEditor::new("one  two three".to_string())
+            .word_left()
+            .word_left();
+
+        assert_eq!(editor.cursor(), 5);
+        let editor = editor.word_right();
+        assert_eq!(editor.cursor(), 8);
+        let editor = editor.kill_word();
+        assert_eq!(editor.text(), "one   three");
+        assert_eq!(editor.kill_to_start().text(), " three");
+    }
+
+    #[test]
+    fn edit_multi_codepoint_graphemes() {
+        let editor = LineEditor::new("ae\u{301}🇫🇷".to_string())
+            .left()
+            .left();
+
+        assert_eq!(editor.cursor(), 1);
+        let editor = editor.delete();
+        assert_eq!(editor.text(), "a🇫🇷");
+        let editor = editor.end().backspace().insert('e').insert('\u{301}');
+        assert_eq!(editor.text(), "ae\u{301}");
+        assert_eq!(editor.left().cursor(), 1);
+    }
+}
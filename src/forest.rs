@@ -1,89 +1,195 @@
-use serde::{Serialize, Deserialize};
+//! A forest of labeled nodes, with each sibling list stored as a balanced
+//! binary tree.
 
-/// A node in a left-child right-sibling binary tree, containing a string.
+use std::{cmp::max, iter::Peekable, mem, ops::Range};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A stable identifier of a node, independent of its position in the forest.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug,
+)]
+pub struct NodeId(u64);
+
+/// Generator of unique node IDs for a forest.
+///
+/// IDs are never reused, even after their nodes are deleted.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct IdGen {
+    next: u64,
+}
+
+/// The contents of a node: a `label`, a stable `id` and whether the node is
+/// `marked`.
 ///
-/// The `size` field stores the size of the node's binary subtree.
-/// The binary tree represents a forest of multi-way trees, where each node can
-/// have any number of children and siblings (the roots are siblings).
+/// Marks are used for multi-selection and are not persisted.
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Item {
+    pub label: String,
+    pub id: NodeId,
+    #[serde(skip)]
+    pub marked: bool,
+}
+
+/// A forest of multi-way trees, whose roots are siblings.
+///
+/// The roots are stored in order as a height-balanced binary tree, where each
+/// node holds an item and its children as a forest. The `size` field stores
+/// the number of nodes in the binary subtree, counting all their descendants,
+/// so a node is found by its pre-order index in logarithmic time per level.
+/// The `marks` field likewise counts the marked nodes, and the `depth` field
+/// stores the greatest depth of the nodes, counting the roots as depth 1.
+/// Forests are equal if they have the same items in the same outline,
+/// whatever the shape of their binary trees.
+#[derive(Clone, Debug)]
 pub enum Node {
     Empty,
     Node {
-        label: String,
+        item: Item,
         child: Box<Node>,
-        sibling: Box<Node>,
+        left: Box<Node>,
+        right: Box<Node>,
         size: usize,
+        marks: usize,
+        depth: usize,
+        height: usize,
     },
 }
 
-// Represents a node in the path from the focused node to the root.
-#[derive(PartialEq, Eq, Debug)]
-enum ReturnNode {
-    Parent { label: String, prev: Box<ReturnNode>, sibling: Node },
-    Sibling { label: String, prev: Box<ReturnNode>, child: Node },
-    Empty,
+// Represents a parent in the path from the roots to the focused position:
+// its `item`, and the trees `before` and `after` it in its sibling list.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct ReturnNode {
+    item: Item,
+    before: Node,
+    after: Node,
 }
 
-// Zipper represention of a forest focused on a node.
+// Zipper represention of a forest focused on a position in a sibling list,
+// splitting the list into the trees `before` the position and the trees from
+// it on, with the `prev` parents of the list from the roots down.
 #[derive(PartialEq, Eq, Debug)]
 struct ForestZipper {
+    before: Node,
     focus: Node,
-    prev: ReturnNode,
+    prev: Vec<ReturnNode>,
+}
+
+/// The rest of a forest whose node at `index` has its children detached.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Hoisted {
+    prev: Vec<ReturnNode>,
+    index: usize,
+}
+
+/// A cursor over a forest, focused on the position of a node or on the end
+/// of a sibling list, for a sequence of local edits.
+///
+/// Moving and editing only touch the nodes next to the position, and the
+/// forest is rebuilt once by `finish`.
+#[derive(Debug)]
+pub struct Cursor {
+    zipper: ForestZipper,
+    index: usize,
 }
 
 // The root of a single tree.
 enum Tree {
-    Root { label: String, child: Node },
+    Root { item: Item, child: Node },
     Empty,
 }
 
 /// Describes whether a node is a root, first child, or non-root right sibling.
+#[derive(Clone, Copy)]
 pub enum NodeType {
     Root,
     Child,
     Sibling,
 }
 
+/// Describes where to insert a forest relative to a target node.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Before,
+    After,
+    FirstChild,
+    LastChild,
+}
+
+/// Describes a structural movement from a node to a related node.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Parent,
+    FirstChild,
+    NextSibling,
+    PrevSibling,
+    FirstRoot,
+    LastRoot,
+    NextAtDepth,
+}
+
 /// Describes the position of a node in a forest (used for display).
 pub struct NodePosition {
     pub node_type: NodeType,
     pub is_last: bool,
 }
 
-// Represents a node containing references and tree position info.
-struct NodeRef<'a> {
-    label: &'a str,
+// A tree of a sibling list on the path to a node: the `start` index and the
+// contents of the tree, the `list` containing it, starting at index `base`,
+// and the indices of its previous and next siblings.
+struct Level<'a> {
+    start: usize,
+    item: &'a Item,
     child: &'a Node,
-    sibling: &'a Node,
-    pos: NodePosition,
+    list: &'a Node,
+    base: usize,
+    prev: Option<usize>,
+    next: Option<usize>,
 }
 
-// Concatenate two trees, making their roots siblings.
+// Iterator over the trees of a sibling list in order, as their root items
+// and children, holding the nodes whose trees are still to come.
+struct Trees<'a> {
+    stack: Vec<&'a Node>,
+}
+
+// Concatenate two forests, making their roots siblings.
 fn concat(left_root: Node, right_root: Node) -> Node {
-    if let Node::Empty = right_root {
-        return left_root;
+    match right_root.split_first() {
+        Some((item, child, rest)) => Node::join(left_root, item, child, rest),
+        None => left_root,
     }
-    let mut focus = left_root;
-    let mut prev = ReturnNode::Empty;
-    while let Node::Node { label, child, sibling, .. } = focus {
-        focus = *sibling;
-        prev = ReturnNode::new_sibling(label, prev, *child);
-    }
-    ForestZipper { focus: right_root, prev }
-        .restore()
 }
 
 impl Node {
-    fn new(label: String, child: Self, sibling: Self) -> Self {
-        let size = 1 + child.size() + sibling.size();
+    // Create a node of a sibling list with the `item` and its `child` forest,
+    // between the `left` and `right` trees, which must be balanced with each
+    // other.
+    fn new(item: Item, child: Self, left: Self, right: Self) -> Self {
+        let size = left.size() + 1 + child.size() + right.size();
+        let marks = left.marks()
+            + usize::from(item.marked)
+            + child.marks()
+            + right.marks();
+        let depth = max(max(left.depth(), 1 + child.depth()), right.depth());
+        let height = 1 + max(left.height(), right.height());
         Self::Node {
-            label,
+            item,
             child: Box::new(child),
-            sibling: Box::new(sibling),
+            left: Box::new(left),
+            right: Box::new(right),
             size,
+            marks,
+            depth,
+            height,
         }
     }
 
+    // Create a forest of a single tree with the `item` and its `child` forest.
+    fn leaf(item: Item, child: Self) -> Self {
+        Self::new(item, child, Self::Empty, Self::Empty)
+    }
+
     /// Return the number of nodes in the forest.
     pub fn size(&self) -> usize {
         match self {
@@ -92,376 +198,1474 @@ impl Node {
         }
     }
 
-    // Return a zipper focused on the node of pre-order `index` in the forest.
-    // If the index is invalid, the zipper will be focused on an empty node
-    // and behavior is undefined.
-    fn focus_node(self: Node, index: usize) -> ForestZipper {
-        let mut i = index;
-        let mut focus = self;
-        let mut prev = ReturnNode::Empty;
-        while i > 0 {
-            match focus {
-                Node::Node { label, child, sibling, .. } => {
-                    if i <= child.size() {
-                        i -= 1;
-                        focus = *child;
-                        prev = ReturnNode::new_parent(label, prev, *sibling);
-                    } else {
-                        i -= 1 + child.size();
-                        focus = *sibling;
-                        prev = ReturnNode::new_sibling(label, prev, *child);
-                    }
-                }
-                Node::Empty => break,
-            }
+    // Return the number of marked nodes in the forest.
+    fn marks(&self) -> usize {
+        match self {
+            Self::Empty => 0,
+            Self::Node { marks, .. } => *marks,
         }
-        ForestZipper { focus, prev }
     }
 
-    /// Return the label at pre-order `index` (panic if invalid).
-    pub fn find_label(&self, index: usize) -> String {
-        let mut i = index;
-        let mut node = self;
-        while i > 0 {
-            match node {
-                Self::Node { child, sibling, .. } => {
-                    if i <= child.size() {
-                        i -= 1;
-                        node = child;
-                    } else {
-                        i -= 1 + child.size();
-                        node = sibling;
-                    }
-                }
-                Self::Empty => break,
-            }
-        }
-        match node {
-            Self::Node { label, .. } => label.clone(),
-            Self::Empty => panic!("Invalid index"),
+    // Return the greatest depth of the nodes, counting the roots as depth 1.
+    fn depth(&self) -> usize {
+        match self {
+            Self::Empty => 0,
+            Self::Node { depth, .. } => *depth,
         }
     }
 
-    /// Assign the `label` to the node at `index`.
-    pub fn set_label(self, index: usize, label: String) -> Self {
-        let ForestZipper { focus, prev } = self.focus_node(index);
-        let focus = match focus {
-            Self::Node { child, sibling, size, .. } => {
-                Self::Node { label, child, sibling, size }
-            }
-            Self::Empty => Self::Empty,
-        };
-        ForestZipper { focus, prev }
-            .restore()
+    // Return the height of the binary tree of the roots.
+    fn height(&self) -> usize {
+        match self {
+            Self::Empty => 0,
+            Self::Node { height, .. } => *height,
+        }
     }
 
-    /// Insert a Node with the `label` at the start of the forest.
-    pub fn prepend(self, label: String) -> Self {
-        Self::new(label, Self::Empty, self)
+    // Rotate the binary tree left, raising its right node.
+    fn rotate_left(self) -> Self {
+        let Self::Node { item, child, left, right, .. } = self else {
+            return self;
+        };
+        match *right {
+            Self::Node {
+                item: item2,
+                child: child2,
+                left: left2,
+                right: right2,
+                ..
+            } => {
+                let left = Self::new(item, *child, *left, *left2);
+                Self::new(item2, *child2, left, *right2)
+            }
+            Self::Empty => Self::new(item, *child, *left, Self::Empty),
+        }
     }
 
-    /// Swap the subtree at `index` with its next sibling.
-    pub fn move_forward(self, index: usize) -> (Self, usize) {
-        self.focus_node(index)
-            .move_forward()
-            .restore_with_index()
+    // Rotate the binary tree right, raising its left node.
+    fn rotate_right(self) -> Self {
+        let Self::Node { item, child, left, right, .. } = self else {
+            return self;
+        };
+        match *left {
+            Self::Node {
+                item: item2,
+                child: child2,
+                left: left2,
+                right: right2,
+                ..
+            } => {
+                let right = Self::new(item, *child, *right2, *right);
+                Self::new(item2, *child2, *left2, right)
+            }
+            Self::Empty => Self::new(item, *child, Self::Empty, *right),
+        }
     }
 
-    /// Swap the subtree at `index` with its previous sibling.
-    pub fn move_backward(self, index: usize) -> (Self, usize) {
-        self.focus_node(index)
-            .move_backward()
-            .restore_with_index()
+    // Return the forest of the `left` trees, the tree with the `item` and its
+    // `child` forest, then the `right` trees, balancing the binary tree.
+    fn join(left: Self, item: Item, child: Self, right: Self) -> Self {
+        if left.height() > right.height() + 1 {
+            Self::join_right(left, item, child, right)
+        } else if right.height() > left.height() + 1 {
+            Self::join_left(left, item, child, right)
+        } else {
+            Self::new(item, child, left, right)
+        }
     }
 
-    /// Move subtree at `index` to be its parent's next sibling.
-    ///
-    /// If it has no parent, move it to be the first tree in the forest.
-    pub fn promote(self, index: usize) -> (Self, usize) {
-        self.focus_node(index)
-            .promote()
-            .restore_with_index()
+    // Join along the right spine of the taller `left` binary tree.
+    fn join_right(left: Self, item: Item, child: Self, right: Self) -> Self {
+        let Self::Node {
+            item: item2,
+            child: child2,
+            left: left2,
+            right: right2,
+            ..
+        } = left
+        else {
+            return Self::new(item, child, left, right);
+        };
+        if right2.height() <= right.height() + 1 {
+            let joined = Self::new(item, child, *right2, right);
+            if joined.height() <= left2.height() + 1 {
+                Self::new(item2, *child2, *left2, joined)
+            } else {
+                Self::new(item2, *child2, *left2, joined.rotate_right())
+                    .rotate_left()
+            }
+        } else {
+            let joined = Self::join_right(*right2, item, child, right);
+            let balanced = joined.height() <= left2.height() + 1;
+            let node = Self::new(item2, *child2, *left2, joined);
+            match balanced {
+                true => node,
+                false => node.rotate_left(),
+            }
+        }
     }
 
-    /// Move subtree at `index` to be its previous sibling's last child.
-    pub fn demote(self, index: usize) -> (Self, usize) {
-        self.focus_node(index)
-            .demote()
-            .restore_with_index()
+    // Join along the left spine of the taller `right` binary tree.
+    fn join_left(left: Self, item: Item, child: Self, right: Self) -> Self {
+        let Self::Node {
+            item: item2,
+            child: child2,
+            left: left2,
+            right: right2,
+            ..
+        } = right
+        else {
+            return Self::new(item, child, left, right);
+        };
+        if left2.height() <= left.height() + 1 {
+            let joined = Self::new(item, child, left, *left2);
+            if joined.height() <= right2.height() + 1 {
+                Self::new(item2, *child2, joined, *right2)
+            } else {
+                Self::new(item2, *child2, joined.rotate_left(), *right2)
+                    .rotate_right()
+            }
+        } else {
+            let joined = Self::join_left(left, item, child, *left2);
+            let balanced = joined.height() <= right2.height() + 1;
+            let node = Self::new(item2, *child2, joined, *right2);
+            match balanced {
+                true => node,
+                false => node.rotate_right(),
+            }
+        }
     }
 
-    /// Delete the node of pre-order `index` from the forest.
-    pub fn delete(self, index: usize) -> Self {
-        let ForestZipper { focus, prev } = self.focus_node(index);
-        let new_focus = match focus {
-            Self::Node { child, sibling, .. } => concat(*child, *sibling),
-            Self::Empty => Self::Empty,
+    // Split off the first tree, returning its item, its children and the
+    // rest of the forest, or None if the forest is empty.
+    fn split_first(self) -> Option<(Item, Self, Self)> {
+        let Self::Node { item, child, left, right, .. } = self else {
+            return None;
         };
-        ForestZipper { focus: new_focus, prev, }
-            .restore()
+        match left.split_first() {
+            Some((first, first_child, rest)) => {
+                let rest = Self::join(rest, item, *child, *right);
+                Some((first, first_child, rest))
+            }
+            None => Some((item, *child, *right)),
+        }
     }
 
-    // Create a corresponding NodeRef from a Node if non-empty.
-    fn to_node(&self, node_type: NodeType) -> Option<NodeRef> {
-        match self {
-            Self::Empty => None,
-            Self::Node { label, child, sibling, .. } => {
-                let is_last = match **sibling {
-                    Self::Empty => true,
-                    Self::Node { .. } => false,
-                };
-                let pos = NodePosition { node_type, is_last };
-                Some(NodeRef { label, child, sibling, pos })
+    // Split off the last tree, returning the rest of the forest, and the item
+    // and children of the last tree, or None if the forest is empty.
+    fn split_last(self) -> Option<(Self, Item, Self)> {
+        let Self::Node { item, child, left, right, .. } = self else {
+            return None;
+        };
+        match right.split_last() {
+            Some((rest, last, last_child)) => {
+                let rest = Self::join(*left, item, *child, rest);
+                Some((rest, last, last_child))
             }
+            None => Some((*left, item, *child)),
         }
     }
 
-    /// Return an iterator over node labels in pre-order.
-    pub fn iter(&self) -> PreOrderIter {
-        let mut stack = Vec::new();
-        if let Some(node) = self.to_node(NodeType::Root) {
-            stack.push(node);
+    // Split the forest around the tree containing the node of pre-order
+    // `index`, returning the trees before it, its item and children, the
+    // trees after it and the index of the node within the tree.
+    // Return None if the index is invalid.
+    fn split_tree(
+        self,
+        index: usize,
+    ) -> Option<(Self, Item, Self, Self, usize)> {
+        let Self::Node { item, child, left, right, .. } = self else {
+            return None;
+        };
+        let start = left.size();
+        let end = start + 1 + child.size();
+        if index < start {
+            let (before, found, found_child, after, i) =
+                left.split_tree(index)?;
+            let after = Self::join(after, item, *child, *right);
+            Some((before, found, found_child, after, i))
+        } else if index < end {
+            Some((*left, item, *child, *right, index - start))
+        } else {
+            let (before, found, found_child, after, i) =
+                right.split_tree(index - end)?;
+            let before = Self::join(*left, item, *child, before);
+            Some((before, found, found_child, after, i))
         }
-        PreOrderIter { stack }
     }
-}
 
-impl ReturnNode {
-    fn new_parent(label: String, prev: Self, sibling: Node) -> Self {
-        Self::Parent {
-            label,
-            prev: Box::new(prev),
-            sibling,
+    // Build a balanced forest from the first `count` of the `trees` in order.
+    fn build_trees(
+        trees: &mut impl Iterator<Item = (Item, Self)>,
+        count: usize,
+    ) -> Self {
+        if count == 0 {
+            return Self::Empty;
         }
+        let left = Self::build_trees(trees, count / 2);
+        let Some((item, child)) = trees.next() else {
+            return left;
+        };
+        let right = Self::build_trees(trees, count - count / 2 - 1);
+        Self::new(item, child, left, right)
     }
 
-    fn new_sibling(label: String, prev: Self, child: Node) -> Self {
-        Self::Sibling {
-            label,
-            prev: Box::new(prev),
-            child,
+    // Build a balanced forest from the `trees` in order.
+    fn from_trees(trees: Vec<(Item, Self)>) -> Self {
+        let count = trees.len();
+        Self::build_trees(&mut trees.into_iter(), count)
+    }
+
+    // Append the trees of the forest in order to `trees`.
+    fn into_trees(self, trees: &mut Vec<(Item, Self)>) {
+        if let Self::Node { item, child, left, right, .. } = self {
+            left.into_trees(trees);
+            trees.push((item, *child));
+            right.into_trees(trees);
         }
     }
-}
 
-impl ForestZipper {
-    // Restore the zipper's corresponding forest.
-    fn restore(self) -> Node {
-        let Self { mut focus, mut prev } = self;
-        loop {
-            prev = match prev {
-                ReturnNode::Parent { label, prev, sibling } => {
-                    focus = Node::new(label, focus, sibling);
-                    *prev
-                }
-                ReturnNode::Sibling { label, prev, child } => {
-                    focus = Node::new(label, child, focus);
-                    *prev
+    // Return an iterator over the trees of the forest in order.
+    fn trees(&self) -> Trees<'_> {
+        self.trees_from(0)
+    }
+
+    // Return an iterator over the trees of the forest from the one whose root
+    // has the pre-order `index`, which must be the index of a root.
+    fn trees_from(&self, index: usize) -> Trees<'_> {
+        let mut stack = Vec::new();
+        let mut node = self;
+        let mut i = index;
+        while let Self::Node { child, left, right, .. } = node {
+            let start = left.size();
+            if i <= start {
+                stack.push(node);
+                if i == start {
+                    break;
                 }
-                ReturnNode::Empty => return focus,
+                node = left;
+            } else {
+                i -= start + 1 + child.size();
+                node = right;
             }
         }
+        Trees { stack }
     }
 
-    // Restore the forest and return the focused node's pre-order index.
-    // Behavior is undefined when the focused node is empty.
-    fn restore_with_index(self) -> (Node, usize) {
-        let Self { mut focus, mut prev } = self;
-        let mut i = 0;
-        loop {
-            prev = match prev {
-                ReturnNode::Parent { label, prev, sibling } => {
-                    i += 1;
-                    focus = Node::new(label, focus, sibling);
-                    *prev
-                }
-                ReturnNode::Sibling { label, prev, child } => {
-                    i += 1 + child.size();
-                    focus = Node::new(label, child, focus);
-                    *prev
-                }
-                ReturnNode::Empty => return (focus, i),
+    // Return the size of the last tree of the forest (zero if empty).
+    fn last_tree_size(&self) -> usize {
+        let mut node = self;
+        while let Self::Node { child, right, .. } = node {
+            match **right {
+                Self::Node { .. } => node = right,
+                Self::Empty => return 1 + child.size(),
             }
         }
+        0
     }
 
-    // Swap the focused node's subtree with its next sibling (if present).
-    fn move_forward(self) -> Self {
-        let Self { focus, prev } = self;
-        let focus = match focus {
-            Node::Node { label, child, sibling, .. } => match *sibling {
-                Node::Node {
-                    label: label2,
-                    child: child2,
-                    sibling: sibling2,
-                    ..
-                } => {
-                    let focus = Node::new(label, *child, *sibling2);
-                    let prev = ReturnNode::new_sibling(label2, prev, *child2);
-                    return Self { focus, prev };
-                }
-                Node::Empty => Node::new(label, *child, *sibling),
+    // Return a zipper focused on the node of pre-order `index` in the forest,
+    // or on the end of the roots if the index is the size of the forest.
+    // If the index is greater, the zipper is focused on the end of the roots.
+    fn focus_node(self: Node, index: usize) -> ForestZipper {
+        let mut i = index;
+        let mut forest = self;
+        let mut prev = Vec::new();
+        loop {
+            if i >= forest.size() {
+                let focus = Self::Empty;
+                return ForestZipper { before: forest, focus, prev };
             }
-            Node::Empty => focus,
-        };
-        Self { focus, prev }
+            let (before, item, child, after, j) = forest.split_tree(i)
+                .expect("Invalid index");
+            if j == 0 {
+                let focus = Self::join(Self::Empty, item, child, after);
+                return ForestZipper { before, focus, prev };
+            }
+            prev.push(ReturnNode { item, before, after });
+            forest = child;
+            i = j - 1;
+        }
     }
 
-    // Swap the focused node's subtree with its previous sibling (if present).
-    fn move_backward(self) -> Self {
-        let Self { focus, prev } = self;
-        if let ReturnNode::Sibling { label, prev, child } = prev {
-            match focus {
-                Node::Node {
-                    label: label2,
-                    child: child2,
-                    sibling: sibling2,
-                    ..
-                } => {
-                    let sibling = Node::new(label, child, *sibling2);
-                    let focus = Node::new(label2, *child2, sibling);
-                    Self { focus, prev: *prev }
-                }
-                Node::Empty => Self {
-                    focus,
-                    prev: ReturnNode::new_sibling(label, *prev, child),
-                },
+    /// Return a cursor focused on the node of pre-order `index`, or on the end
+    /// of the roots if `index` is the size of the forest (panic if greater).
+    pub fn cursor(self, index: usize) -> Cursor {
+        assert!(index <= self.size(), "Invalid index");
+        Cursor { zipper: self.focus_node(index), index }
+    }
+
+    // Return the item and children of the node of pre-order `index` in the
+    // forest, or None if the index is invalid.
+    fn find_tree(&self, index: usize) -> Option<(&Item, &Self)> {
+        let mut i = index;
+        let mut node = self;
+        while let Self::Node { item, child, left, right, .. } = node {
+            let start = left.size();
+            if i < start {
+                node = left;
+            } else if i == start {
+                return Some((item, child));
+            } else if i - start <= child.size() {
+                i -= start + 1;
+                node = child;
+            } else {
+                i -= start + 1 + child.size();
+                node = right;
             }
+        }
+        None
+    }
+
+    // Return the item of the first root for editing, or None if empty.
+    // Its mark must be recounted by `update_item` after any change.
+    fn first_item_mut(&mut self) -> Option<&mut Item> {
+        match self {
+            Self::Node { item, left, .. } => match **left {
+                Self::Empty => Some(item),
+                _ => left.first_item_mut(),
+            },
+            Self::Empty => None,
+        }
+    }
+
+    // Apply `f` to the item of the node of pre-order `index`, if valid, and
+    // recount the marks of the nodes on the way to it.
+    fn update_item(&mut self, index: usize, f: impl FnOnce(&mut Item)) {
+        let Self::Node { item, child, left, right, marks, .. } = self else {
+            return;
+        };
+        let start = left.size();
+        if index < start {
+            left.update_item(index, f);
+        } else if index == start {
+            f(item);
+        } else if index - start <= child.size() {
+            child.update_item(index - start - 1, f);
         } else {
-            Self { focus, prev }
+            right.update_item(index - start - 1 - child.size(), f);
         }
+        *marks = left.marks()
+            + usize::from(item.marked)
+            + child.marks()
+            + right.marks();
     }
 
-    // Extract the subtree of the focused node from the forest.
-    fn extract_tree(self) -> (Self, Tree) {
-        match self.focus {
-            Node::Node { label, child, sibling, .. } => {
-                let zipper = Self { focus: *sibling, ..self };
-                let tree = Tree::Root { label, child: *child };
-                (zipper, tree)
-            }
-            Node::Empty => (self, Tree::Empty),
+    /// Return the label at pre-order `index` (panic if invalid).
+    pub fn find_label(&self, index: usize) -> String {
+        match self.find_tree(index) {
+            Some((item, _)) => item.label.clone(),
+            None => panic!("Invalid index"),
         }
     }
 
-    // Move the focused node's subtree to be its parent's next sibling.
-    // If it has no parent, move it to be the first tree in the forest.
-    fn promote(self) -> Self {
-        let (zipper, tree) = self.extract_tree();
-        let (root_label, root_child) = match tree {
-            Tree::Root { label, child } => (label, child),
-            Tree::Empty => return zipper,
+    /// Return the number of nodes in the subtree at `index`.
+    pub fn tree_size(&self, index: usize) -> usize {
+        match self.find_tree(index) {
+            Some((_, child)) => 1 + child.size(),
+            None => 0,
+        }
+    }
+
+    /// Return whether `index` is in the subtree rooted at `root_index`.
+    pub fn in_subtree(&self, root_index: usize, index: usize) -> bool {
+        root_index <= index && index < root_index + self.tree_size(root_index)
+    }
+
+    // Return an iterator over the tree at `index` and its next siblings.
+    fn siblings(&self, index: usize) -> Trees<'_> {
+        match self.path(index).pop() {
+            Some(level) => level.list.trees_from(level.start - level.base),
+            None => Trees { stack: Vec::new() },
+        }
+    }
+
+    // Return the index of the first node at `depth` below the trees from
+    // index `from` on, relative to the first tree of the forest.
+    // Subtrees too shallow to contain such a node are skipped by their depth,
+    // and any other subtree contains one, so the search descends through the
+    // sibling lists in logarithmic time per level.
+    fn first_at_depth(&self, from: usize, depth: usize) -> Option<usize> {
+        let Self::Node { child, left, right, .. } = self else {
+            return None;
         };
-        let Self { mut focus, mut prev } = zipper;
-        loop {
-            match prev {
-                ReturnNode::Sibling { label, prev: prev2, child } => {
-                    focus = Node::new(label, child, focus);
-                    prev = *prev2;
+        if self.depth() <= depth {
+            return None;
+        }
+        let start = left.size();
+        let end = start + 1 + child.size();
+        if from < start {
+            if let Some(found) = left.first_at_depth(from, depth) {
+                return Some(found);
+            }
+        }
+        if from <= start {
+            if depth == 0 {
+                return Some(start);
+            }
+            if let Some(found) = child.first_at_depth(0, depth - 1) {
+                return Some(start + 1 + found);
+            }
+        }
+        right.first_at_depth(from.saturating_sub(end), depth)
+            .map(|found| end + found)
+    }
+
+    /// Return the number of trees from `index` to its last sibling, inclusive.
+    pub fn sibling_count(&self, index: usize) -> usize {
+        self.siblings(index).count()
+    }
+
+    /// Return whether there are more than `count` trees from `index` to its
+    /// last sibling, skipping only those `count` trees.
+    pub fn has_more_siblings(&self, index: usize, count: usize) -> bool {
+        self.siblings(index).nth(count).is_some()
+    }
+
+    /// Return the number of nodes in `count` sibling trees starting at `index`.
+    pub fn siblings_size(&self, index: usize, count: usize) -> usize {
+        self.siblings(index)
+            .take(count)
+            .map(|(_, child)| 1 + child.size())
+            .sum()
+    }
+
+    // Return the tree of each ancestor of the node at `index`, followed by the
+    // tree of the node itself.
+    // This follows the same descent as `focus_node`, by the subtree sizes.
+    // The path is empty if the index is invalid.
+    fn path(&self, index: usize) -> Vec<Level<'_>> {
+        let mut path = Vec::new();
+        let mut list = self;
+        let mut base = 0;
+        let mut node = list;
+        let mut i = base;
+        let (mut prev, mut next) = (None, None);
+        while let Self::Node { item, child, left, right, .. } = node {
+            let start = i + left.size();
+            let end = start + 1 + child.size();
+            if index < start {
+                next = Some(start);
+                node = left;
+            } else if index < end {
+                if let Self::Node { .. } = **left {
+                    prev = Some(start - left.last_tree_size());
                 }
-                ReturnNode::Parent { label, prev, sibling } => {
-                    let prev = ReturnNode::new_sibling(label, *prev, focus);
-                    let focus = Node::new(root_label, root_child, sibling);
-                    return Self { focus, prev };
+                if let Self::Node { .. } = **right {
+                    next = Some(end);
                 }
-                ReturnNode::Empty => {
-                    let focus = Node::new(root_label, root_child, focus);
-                    return Self { focus, prev };
+                path.push(Level { start, item, child, list, base, prev, next });
+                if index == start {
+                    return path;
                 }
-            };
+                (list, base) = (child, start + 1);
+                (node, i) = (list, base);
+                (prev, next) = (None, None);
+            } else {
+                prev = Some(start);
+                node = right;
+                i = end;
+            }
         }
+        Vec::new()
     }
 
-    // Move the focused node's subtree to be its previous sibling's last child.
-    fn demote(self) -> Self {
-        let (zipper, tree) = self.extract_tree();
-        let (root_label, root_child) = match tree {
-            Tree::Root { label, child } => (label, child),
-            Tree::Empty => return zipper,
+    /// Detach the children of the node at `index` (which must be valid).
+    ///
+    /// Return the children as a forest and the rest of the forest.
+    pub fn hoist(self, index: usize) -> (Self, Hoisted) {
+        let ForestZipper { focus, prev, .. } = self
+            .focus_node(index)
+            .focus_child();
+        (focus, Hoisted { prev, index })
+    }
+
+    /// Return the labels of the ancestors of the node at `index`, from its
+    /// root down to the node itself (empty if the index is invalid).
+    pub fn path_labels(&self, index: usize) -> Vec<&str> {
+        self.path(index)
+            .into_iter()
+            .map(|level| level.item.label.as_str())
+            .collect()
+    }
+
+    /// Return the index reached by the `motion` from the node at `index`.
+    ///
+    /// The path to the node is found by descending through the subtree sizes,
+    /// and `NextAtDepth` skips shallow subtrees by their depths, so no motion
+    /// scans the whole forest.
+    /// If there is no such node, `index` is returned unchanged.
+    pub fn jump(&self, index: usize, motion: Motion) -> usize {
+        let path = self.path(index);
+        let Some(last) = path.last() else {
+            return index;
         };
-        let Self { focus, prev } = zipper;
-        if let ReturnNode::Sibling { label, prev, child } = prev {
-            let mut prev = ReturnNode::new_parent(label, *prev, focus);
-            let mut focus = child;
-            while let Node::Node { label, child, sibling, .. } = focus {
-                prev = ReturnNode::new_sibling(label, prev, *child);
-                focus = *sibling;
-            }
-            focus = Node::new(root_label, root_child, Node::Empty);
-            Self { focus, prev }
-        } else {
-            let focus = Node::new(root_label, root_child, focus);
-            Self { focus, prev }
+        let target = match motion {
+            Motion::Parent => path.iter().rev().nth(1).map(|level| level.start),
+            Motion::FirstChild => match last.child.size() {
+                0 => None,
+                _ => Some(last.start + 1),
+            },
+            Motion::NextSibling => last.next,
+            Motion::PrevSibling => last.prev,
+            Motion::FirstRoot => Some(0),
+            Motion::LastRoot => Some(self.size() - self.last_tree_size()),
+            Motion::NextAtDepth => {
+                let depth = path.len() - 1;
+                path.iter()
+                    .enumerate()
+                    .rev()
+                    .find_map(|(level, tree)| {
+                        let start = tree.next? - tree.base;
+                        tree.list
+                            .first_at_depth(start, depth - level)
+                            .map(|found| tree.base + found)
+                    })
+            }
+        };
+        target.unwrap_or(index)
+    }
+
+    /// Return a copy of the subtree at `index` as a single-tree forest.
+    pub fn copy_tree(&self, index: usize) -> Self {
+        match self.find_tree(index) {
+            Some((item, child)) => Self::leaf(item.clone(), child.clone()),
+            None => Self::Empty,
         }
     }
-}
 
-/// Iterator type returning node labels/positions in pre-order.
-pub struct PreOrderIter<'a> {
-    stack: Vec<NodeRef<'a>>,
-}
+    // Apply `f` to the item of the node at `index`.
+    fn map_item(mut self, index: usize, f: impl FnOnce(&mut Item)) -> Self {
+        self.update_item(index, f);
+        self
+    }
 
-impl<'a> Iterator for PreOrderIter<'a> {
-    type Item = (&'a str, NodePosition);
+    /// Assign the `label` to the node at `index`.
+    pub fn set_label(self, index: usize, label: String) -> Self {
+        self.map_item(index, |item| item.label = label)
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let NodeRef { label, child, sibling, pos } = self.stack.pop()?;
-        let sibling_type = match pos.node_type {
-            NodeType::Root => NodeType::Root,
-            _ => NodeType::Sibling,
-        };
-        if let Some(node) = sibling.to_node(sibling_type) {
-            self.stack.push(node);
+    /// Set whether the node at `index` is marked.
+    pub fn set_mark(self, index: usize, marked: bool) -> Self {
+        self.map_item(index, |item| item.marked = marked)
+    }
+
+    /// Toggle whether the node at `index` is marked.
+    pub fn toggle_mark(self, index: usize) -> Self {
+        self.map_item(index, |item| item.marked = !item.marked)
+    }
+
+    /// Return the number of marked nodes in the forest, in constant time.
+    pub fn mark_count(&self) -> usize {
+        self.marks()
+    }
+
+    /// Remove the marks from all nodes in the forest.
+    pub fn clear_marks(self) -> Self {
+        self.map_marked(&mut |item| Item { marked: false, ..item })
+    }
+
+    /// Apply `f` to every marked item in the forest in pre-order.
+    ///
+    /// Only the subtrees containing marks are rebuilt, keeping their shape.
+    pub fn map_marked(self, f: &mut impl FnMut(Item) -> Item) -> Self {
+        if self.marks() == 0 {
+            return self;
         }
-        if let Some(node) = child.to_node(NodeType::Child) {
-            self.stack.push(node);
+        let Self::Node { item, child, left, right, .. } = self else {
+            return self;
+        };
+        let left = left.map_marked(f);
+        let item = match item.marked {
+            true => f(item),
+            false => item,
+        };
+        let child = child.map_marked(f);
+        Self::new(item, child, left, right.map_marked(f))
+    }
+
+    /// Return the ID of the node at `index` (panic if invalid).
+    pub fn find_id(&self, index: usize) -> NodeId {
+        match self.find_tree(index) {
+            Some((item, _)) => item.id,
+            None => panic!("Invalid index"),
         }
-        Some((label, pos))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Return the current pre-order index of the node with the `id`, if any.
+    ///
+    /// The forest is searched in pre-order, taking linear time.
+    pub fn find_index(&self, id: NodeId) -> Option<usize> {
+        self.iter()
+            .position(|(item, _)| item.id == id)
+    }
 
-    // Create a forest from a list of trees.
-    fn forest(mut trees: Vec<Tree>) -> Node {
-        let mut root = Node::Empty;
-        while let Some(tree) = trees.pop() {
-            if let Tree::Root { label, child } = tree {
-                root = Node::new(label, child, root);
+    /// Return an iterator over the node IDs in pre-order.
+    pub fn ids(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.iter()
+            .map(|(item, _)| item.id)
+    }
+
+    /// Assign a new ID from `ids` to every node in the forest.
+    pub fn renumber(self, ids: &mut IdGen) -> Self {
+        self.map_items(&mut |item| Item { id: ids.next_id(), ..item })
+    }
+
+    /// Return the index ranges of the marked subtrees in pre-order, omitting
+    /// those contained in another marked subtree.
+    pub fn marked_trees(&self) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        let mut stack = vec![(self.trees(), 0)];
+        while let Some((trees, i)) = stack.last_mut() {
+            let Some((item, child)) = trees.next() else {
+                stack.pop();
+                continue;
+            };
+            let start = *i;
+            let end = start + 1 + child.size();
+            *i = end;
+            match item.marked {
+                true => ranges.push(start..end),
+                false => stack.push((child.trees(), start + 1)),
             }
         }
-        root
+        ranges
     }
 
-    // Create a tree given the root `label` and list of child subtrees.
-    fn tree(label: &str, children: Vec<Tree>) -> Tree {
-        Tree::Root {
-            label: label.to_string(),
-            child: forest(children),
+    /// Remove the marked subtrees from the forest.
+    ///
+    /// Return the remaining forest and a forest of the removed subtrees.
+    pub fn extract_marked(self) -> (Self, Self) {
+        let ranges = self.marked_trees();
+        let mut rest = self;
+        let mut extracted = Self::Empty;
+        for range in ranges.into_iter().rev() {
+            let (new_rest, tree) = rest.extract(range.start);
+            rest = new_rest;
+            extracted = concat(tree, extracted);
         }
+        (rest, extracted)
     }
 
-    // Create a single-node tree.
-    fn leaf(label: &str) -> Tree {
-        Tree::Root {
-            label: label.to_string(),
-            child: Node::Empty,
+    /// Move the marked subtrees to the `placement` relative to `target`.
+    ///
+    /// Return the new forest and the index of the first moved subtree.
+    /// If `target` is within a marked subtree, the forest is unchanged.
+    pub fn move_marked(
+        self,
+        target: usize,
+        placement: Placement,
+    ) -> (Self, usize) {
+        let ranges = self.marked_trees();
+        if ranges.is_empty() || ranges.iter().any(|r| r.contains(&target)) {
+            return (self, target);
         }
+        let shift: usize = ranges.iter()
+            .filter(|range| range.start < target)
+            .map(|range| range.len())
+            .sum();
+        let (rest, extracted) = self.extract_marked();
+        rest.insert(target - shift, extracted, placement)
     }
 
-    #[test]
-    fn focus_empty_forest() {
-        let result_0 = Node::Empty.focus_node(0);
-        let result_1 = Node::Empty.focus_node(1);
-        let empty_zipper = ForestZipper {
-            focus: Node::Empty,
-            prev: ReturnNode::Empty,
-        };
-
-        assert_eq!(result_0, empty_zipper);
-        assert_eq!(result_1, empty_zipper);
+    /// Sort the children of the node at `index` by label.
+    pub fn sort_children(self, index: usize) -> Self {
+        let ForestZipper { before, focus, prev } = self
+            .focus_node(index)
+            .focus_child();
+        ForestZipper { before, focus: focus.sort_trees(), prev }
+            .restore()
+    }
+
+    /// Sort the children of each marked node by label.
+    pub fn sort_marked(self) -> Self {
+        let mut trees = Vec::new();
+        self.into_trees(&mut trees);
+        let trees = trees.into_iter()
+            .map(|(item, child)| {
+                let child = match item.marked {
+                    true => child.sort_marked().sort_trees(),
+                    false => child.sort_marked(),
+                };
+                (item, child)
+            })
+            .collect();
+        Self::from_trees(trees)
+    }
+
+    // Sort the trees of the forest by their root labels, ignoring case.
+    fn sort_trees(self) -> Self {
+        let mut trees = Vec::new();
+        self.into_trees(&mut trees);
+        trees.sort_by_cached_key(|(item, _)| item.label.to_lowercase());
+        Self::from_trees(trees)
+    }
+
+    /// Build a forest from `items` in pre-order, each paired with its depth.
+    ///
+    /// An item deeper than the one before it becomes its child.
+    pub fn from_outline(
+        items: impl IntoIterator<Item = (usize, Item)>,
+    ) -> Self {
+        let mut items = items.into_iter().peekable();
+        Self::build_outline(&mut items, 0)
+    }
+
+    // Build the sibling trees at `depth` or deeper from the start of `items`.
+    fn build_outline(
+        items: &mut Peekable<impl Iterator<Item = (usize, Item)>>,
+        depth: usize,
+    ) -> Self {
+        let mut trees = Vec::new();
+        while let Some((item_depth, item)) = items
+            .next_if(|&(item_depth, _)| item_depth >= depth)
+        {
+            let child = Self::build_outline(items, item_depth + 1);
+            trees.push((item, child));
+        }
+        Self::from_trees(trees)
+    }
+
+    /// Return the labels of the forest in pre-order, each paired with its
+    /// depth.
+    pub fn outline(&self) -> Vec<(usize, &str)> {
+        self.items()
+            .into_iter()
+            .map(|(depth, item)| (depth, item.label.as_str()))
+            .collect()
+    }
+
+    // Return the items of the forest in pre-order, each paired with its depth.
+    fn items(&self) -> Vec<(usize, &Item)> {
+        let mut items = Vec::new();
+        self.push_items(0, &mut items);
+        items
+    }
+
+    // Append the items of the sibling trees at `depth` to `items`.
+    fn push_items<'a>(
+        &'a self,
+        depth: usize,
+        items: &mut Vec<(usize, &'a Item)>,
+    ) {
+        for (item, child) in self.trees() {
+            items.push((depth, item));
+            child.push_items(depth + 1, items);
+        }
+    }
+
+    /// Insert a Node with the `item` at the start of the forest.
+    pub fn prepend(self, item: Item) -> Self {
+        Self::join(Self::Empty, item, Self::Empty, self)
+    }
+
+    /// Swap the subtree at `index` with its next sibling.
+    pub fn move_forward(self, index: usize) -> (Self, usize) {
+        self.focus_node(index)
+            .move_forward()
+            .restore_with_index()
+    }
+
+    /// Swap the subtree at `index` with its previous sibling.
+    pub fn move_backward(self, index: usize) -> (Self, usize) {
+        self.focus_node(index)
+            .move_backward()
+            .restore_with_index()
+    }
+
+    /// Move subtree at `index` to be its parent's next sibling.
+    ///
+    /// If it has no parent, move it to be the first tree in the forest.
+    pub fn promote(self, index: usize) -> (Self, usize) {
+        self.focus_node(index)
+            .promote()
+            .restore_with_index()
+    }
+
+    /// Move subtree at `index` to be its previous sibling's last child.
+    pub fn demote(self, index: usize) -> (Self, usize) {
+        self.focus_node(index)
+            .demote()
+            .restore_with_index()
+    }
+
+    /// Remove the subtree at `index` from the forest.
+    ///
+    /// Return the remaining forest and the removed subtree as a forest.
+    pub fn extract(self, index: usize) -> (Self, Self) {
+        let (zipper, tree) = self.focus_node(index).extract_tree();
+        (zipper.restore(), tree.into_forest())
+    }
+
+    /// Insert the trees of `forest` at the `placement` relative to `index`.
+    ///
+    /// Return the new forest and the index of the first inserted tree.
+    /// An empty `forest` leaves the forest and index unchanged.
+    pub fn insert(
+        self,
+        index: usize,
+        forest: Self,
+        placement: Placement,
+    ) -> (Self, usize) {
+        if let Self::Empty = forest {
+            return (self, index);
+        }
+        let zipper = self.focus_node(index);
+        let zipper = match placement {
+            Placement::Before => zipper,
+            Placement::After => zipper.focus_sibling(),
+            Placement::FirstChild => zipper.focus_child(),
+            Placement::LastChild => zipper.focus_child_end(),
+        };
+        zipper
+            .insert(forest)
+            .restore_with_index()
+    }
+
+    /// Move the subtree at `source` to the `placement` relative to `target`.
+    ///
+    /// Return the new forest and the moved subtree's index.
+    /// If `target` is within the source subtree, the forest is unchanged.
+    pub fn move_to(
+        self,
+        source: usize,
+        target: usize,
+        placement: Placement,
+    ) -> (Self, usize) {
+        if self.in_subtree(source, target) {
+            return (self, source);
+        }
+        let tree_size = self.tree_size(source);
+        let (rest, subtree) = self.extract(source);
+        let target = match target > source {
+            true => target - tree_size,
+            false => target,
+        };
+        rest.insert(target, subtree, placement)
+    }
+
+    /// Insert a copy of the subtree at `index` as its next sibling, applying
+    /// `rewrite` to each copied label and assigning new IDs from `ids`.
+    ///
+    /// Return the new forest and the index of the copy.
+    pub fn duplicate(
+        self,
+        index: usize,
+        ids: &mut IdGen,
+        rewrite: impl Fn(String) -> String,
+    ) -> (Self, usize) {
+        let copy = self.copy_tree(index)
+            .map_items(&mut |item| {
+                Item::new(ids.next_id(), rewrite(item.label))
+            });
+        self.insert(index, copy, Placement::After)
+    }
+
+    /// Apply `f` to every item in the forest in pre-order.
+    pub fn map_items(self, f: &mut impl FnMut(Item) -> Item) -> Self {
+        let mut trees = Vec::new();
+        self.into_trees(&mut trees);
+        let trees = trees.into_iter()
+            .map(|(item, child)| {
+                let item = f(item);
+                (item, child.map_items(f))
+            })
+            .collect();
+        Self::from_trees(trees)
+    }
+
+    /// Move `count` sibling trees starting at `index` under a new parent with
+    /// the `item`, which takes the place of the first tree.
+    pub fn wrap(self, index: usize, count: usize, item: Item) -> Self {
+        self.focus_node(index)
+            .wrap(count, item)
+            .restore()
+    }
+
+    /// Delete the subtree at `index`, including all of its descendants.
+    pub fn delete_tree(self, index: usize) -> Self {
+        let (forest, _) = self.extract(index);
+        forest
+    }
+
+    /// Delete the node of pre-order `index` from the forest.
+    pub fn delete(self, index: usize) -> Self {
+        let ForestZipper { before, focus, prev } = self.focus_node(index);
+        let focus = match focus.split_first() {
+            Some((_, child, rest)) => concat(child, rest),
+            None => Self::Empty,
+        };
+        ForestZipper { before, focus, prev }
+            .restore()
+    }
+
+    /// Return an iterator over node labels in pre-order.
+    pub fn iter(&self) -> PreOrderIter<'_> {
+        PreOrderIter { stack: vec![(self.trees(), NodeType::Root)] }
+    }
+
+    /// Return an iterator over node labels in pre-order from the node at
+    /// `index` (empty if the index is invalid).
+    ///
+    /// The node is found by descending through the subtree sizes, in
+    /// logarithmic time per level.
+    pub fn iter_from(&self, index: usize) -> PreOrderIter<'_> {
+        let path = self.path(index);
+        let last = path.len().saturating_sub(1);
+        let stack = path.iter()
+            .enumerate()
+            .map(|(depth, level)| {
+                let start = level.start - level.base;
+                // Ancestors resume after their own subtree, where only their
+                // later siblings remain.
+                let after = start + 1 + level.child.size();
+                let (from, node_type) = match (depth == last, depth) {
+                    (true, _) => (start, level.node_type(depth)),
+                    (false, 0) => (after, NodeType::Root),
+                    (false, _) => (after, NodeType::Sibling),
+                };
+                (level.list.trees_from(from), node_type)
+            })
+            .collect();
+        PreOrderIter { stack }
+    }
+
+    /// Return the position of each ancestor of the node at `index`, from its
+    /// root down, followed by the position of the node itself (empty if the
+    /// index is invalid).
+    pub fn path_positions(&self, index: usize) -> Vec<NodePosition> {
+        self.path(index)
+            .iter()
+            .enumerate()
+            .map(|(depth, level)| NodePosition {
+                node_type: level.node_type(depth),
+                is_last: level.next.is_none(),
+            })
+            .collect()
+    }
+}
+
+impl Level<'_> {
+    // Return the type of the tree, which is at `depth` in the forest.
+    fn node_type(&self, depth: usize) -> NodeType {
+        match (depth, self.start == self.base) {
+            (0, _) => NodeType::Root,
+            (_, true) => NodeType::Child,
+            (_, false) => NodeType::Sibling,
+        }
+    }
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.size() == other.size() && self.items() == other.items()
+    }
+}
+
+impl Eq for Node {}
+
+// A forest is stored as its items in pre-order, each paired with its depth,
+// independent of the shape of its binary trees.
+impl Serialize for Node {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.items())
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let items = Vec::<(usize, Item)>::deserialize(deserializer)?;
+        Ok(Self::from_outline(items))
+    }
+}
+
+impl Hoisted {
+    /// Return the label of the hoisted node.
+    pub fn label(&self) -> &str {
+        self.prev
+            .last()
+            .map_or("", |parent| &parent.item.label)
+    }
+
+    /// Return the IDs of all nodes outside the detached children.
+    pub fn ids(&self) -> Vec<NodeId> {
+        let mut ids = Vec::new();
+        for ReturnNode { item, before, after } in &self.prev {
+            ids.push(item.id);
+            ids.extend(before.ids());
+            ids.extend(after.ids());
+        }
+        ids
+    }
+
+    /// Reattach the `forest` as the children of the hoisted node.
+    ///
+    /// Return the full forest and the index of the hoisted node.
+    pub fn unhoist(self, forest: Node) -> (Node, usize) {
+        let zipper = ForestZipper {
+            before: Node::Empty,
+            focus: forest,
+            prev: self.prev,
+        };
+        (zipper.restore(), self.index)
+    }
+}
+
+impl IdGen {
+    /// Create an IdGen that generates IDs not already used in the `forest`.
+    pub fn new(forest: &Node) -> Self {
+        let mut ids = IdGen::default();
+        ids.exclude(forest);
+        ids
+    }
+
+    /// Return a new unique ID.
+    pub fn next_id(&mut self) -> NodeId {
+        let id = NodeId(self.next);
+        self.next += 1;
+        id
+    }
+
+    /// Ensure that no ID used in the `forest` is generated in the future.
+    pub fn exclude(&mut self, forest: &Node) {
+        if let Some(NodeId(max)) = forest.ids().max_by_key(|id| id.0) {
+            self.next = self.next.max(max + 1);
+        }
+    }
+}
+
+impl Item {
+    /// Create an unmarked Item with the `id` and `label`.
+    pub fn new(id: NodeId, label: String) -> Self {
+        Item { label, id, marked: false }
+    }
+}
+
+impl Tree {
+    // Convert the tree into a forest containing only this tree.
+    fn into_forest(self) -> Node {
+        match self {
+            Tree::Root { item, child } => Node::leaf(item, child),
+            Tree::Empty => Node::Empty,
+        }
+    }
+}
+
+impl ForestZipper {
+    // Restore the zipper's corresponding forest.
+    fn restore(self) -> Node {
+        let Self { before, focus, prev } = self;
+        let mut forest = concat(before, focus);
+        for ReturnNode { item, before, after } in prev.into_iter().rev() {
+            forest = Node::join(before, item, forest, after);
+        }
+        forest
+    }
+
+    // Restore the forest and return the focused position's pre-order index.
+    fn restore_with_index(self) -> (Node, usize) {
+        let i = self.prev
+            .iter()
+            .fold(self.before.size(), |i, parent| i + parent.before.size() + 1);
+        (self.restore(), i)
+    }
+
+    // Move the focus to the first child position of the focused node.
+    fn focus_child(self) -> Self {
+        let Self { before, focus, mut prev } = self;
+        match focus.split_first() {
+            Some((item, child, after)) => {
+                prev.push(ReturnNode { item, before, after });
+                Self { before: Node::Empty, focus: child, prev }
+            }
+            None => Self { before, focus: Node::Empty, prev },
+        }
+    }
+
+    // Move the focus to the next sibling position of the focused node.
+    fn focus_sibling(self) -> Self {
+        let Self { before, focus, prev } = self;
+        match focus.split_first() {
+            Some((item, child, focus)) => {
+                let before = Node::join(before, item, child, Node::Empty);
+                Self { before, focus, prev }
+            }
+            None => Self { before, focus: Node::Empty, prev },
+        }
+    }
+
+    // Move the focus to the previous sibling position of the focused node.
+    fn focus_prev_sibling(self) -> Self {
+        let Self { before, focus, prev } = self;
+        match before.split_last() {
+            Some((before, item, child)) => {
+                let focus = Node::join(Node::Empty, item, child, focus);
+                Self { before, focus, prev }
+            }
+            None => Self { before: Node::Empty, focus, prev },
+        }
+    }
+
+    // Move the focus to the parent of the focused position, if any.
+    fn focus_parent(self) -> Self {
+        let Self { before, focus, mut prev } = self;
+        match prev.pop() {
+            Some(ReturnNode { item, before: before2, after }) => {
+                let child = concat(before, focus);
+                let focus = Node::join(Node::Empty, item, child, after);
+                Self { before: before2, focus, prev }
+            }
+            None => Self { before, focus, prev },
+        }
+    }
+
+    // Move the focus to the position after the focused node's last child.
+    fn focus_child_end(self) -> Self {
+        let Self { before, focus, prev } = self.focus_child();
+        Self { before: concat(before, focus), focus: Node::Empty, prev }
+    }
+
+    // Insert the trees of `forest` before the focused node.
+    // The focus moves to the first inserted tree.
+    fn insert(self, forest: Node) -> Self {
+        let Self { before, focus, prev } = self;
+        Self { before, focus: concat(forest, focus), prev }
+    }
+
+    // Move the focused tree and its next `count - 1` siblings under a new
+    // parent with the `item`, focusing on the new parent.
+    fn wrap(self, count: usize, item: Item) -> Self {
+        let Self { before, mut focus, prev } = self;
+        let mut wrapped = Node::Empty;
+        for _ in 0..count {
+            let Some((item, child, rest)) = focus.split_first() else {
+                focus = Node::Empty;
+                break;
+            };
+            wrapped = Node::join(wrapped, item, child, Node::Empty);
+            focus = rest;
+        }
+        let focus = Node::join(Node::Empty, item, wrapped, focus);
+        Self { before, focus, prev }
+    }
+
+    // Swap the focused node's subtree with its next sibling (if present).
+    fn move_forward(self) -> Self {
+        let Self { before, focus, prev } = self;
+        let Some((item, child, rest)) = focus.split_first() else {
+            return Self { before, focus: Node::Empty, prev };
+        };
+        match rest.split_first() {
+            Some((item2, child2, rest2)) => {
+                let before = Node::join(before, item2, child2, Node::Empty);
+                let focus = Node::join(Node::Empty, item, child, rest2);
+                Self { before, focus, prev }
+            }
+            None => Self { before, focus: Node::leaf(item, child), prev },
+        }
+    }
+
+    // Swap the focused node's subtree with its previous sibling (if present).
+    fn move_backward(self) -> Self {
+        let Self { before, focus, prev } = self;
+        let Some((before, item, child)) = before.split_last() else {
+            return Self { before: Node::Empty, focus, prev };
+        };
+        match focus.split_first() {
+            Some((item2, child2, rest)) => {
+                let sibling = Node::join(Node::Empty, item, child, rest);
+                let focus = Node::join(Node::Empty, item2, child2, sibling);
+                Self { before, focus, prev }
+            }
+            None => {
+                let before = Node::join(before, item, child, Node::Empty);
+                Self { before, focus: Node::Empty, prev }
+            }
+        }
+    }
+
+    // Extract the subtree of the focused node from the forest.
+    fn extract_tree(self) -> (Self, Tree) {
+        let Self { before, focus, prev } = self;
+        match focus.split_first() {
+            Some((item, child, focus)) => {
+                let tree = Tree::Root { item, child };
+                (Self { before, focus, prev }, tree)
+            }
+            None => (Self { before, focus: Node::Empty, prev }, Tree::Empty),
+        }
+    }
+
+    // Move the focused node's subtree to be its parent's next sibling.
+    // If it has no parent, move it to be the first tree in the forest.
+    fn promote(self) -> Self {
+        let (zipper, tree) = self.extract_tree();
+        let (root_item, root_child) = match tree {
+            Tree::Root { item, child } => (item, child),
+            Tree::Empty => return zipper,
+        };
+        let Self { before, focus, mut prev } = zipper;
+        let siblings = concat(before, focus);
+        let (before, after) = match prev.pop() {
+            Some(ReturnNode { item, before, after }) => {
+                (Node::join(before, item, siblings, Node::Empty), after)
+            }
+            None => (Node::Empty, siblings),
+        };
+        let focus = Node::join(Node::Empty, root_item, root_child, after);
+        Self { before, focus, prev }
+    }
+
+    // Move the focused node's subtree to be its previous sibling's last child.
+    fn demote(self) -> Self {
+        let (zipper, tree) = self.extract_tree();
+        let (root_item, root_child) = match tree {
+            Tree::Root { item, child } => (item, child),
+            Tree::Empty => return zipper,
+        };
+        let Self { before, focus, mut prev } = zipper;
+        match before.split_last() {
+            Some((before, item, child)) => {
+                prev.push(ReturnNode { item, before, after: focus });
+                let focus = Node::leaf(root_item, root_child);
+                Self { before: child, focus, prev }
+            }
+            None => {
+                let focus =
+                    Node::join(Node::Empty, root_item, root_child, focus);
+                Self { before: Node::Empty, focus, prev }
+            }
+        }
+    }
+}
+
+impl Cursor {
+    /// Return the pre-order index of the focused position, which is the
+    /// index a tree inserted there would have.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Return the focused item, or None at the end of a sibling list.
+    pub fn item(&self) -> Option<&Item> {
+        self.zipper.focus
+            .find_tree(0)
+            .map(|(item, _)| item)
+    }
+
+    /// Return the focused item for editing, or None at the end of a sibling
+    /// list.
+    pub fn item_mut(&mut self) -> Option<&mut Item> {
+        self.zipper.focus.first_item_mut()
+    }
+
+    // Recount the marks on the way to the focused item, which may have been
+    // changed through `item_mut`.
+    fn recount_marks(&mut self) {
+        self.zipper.focus.update_item(0, |_| ());
+    }
+
+    /// Return the number of nodes in the focused subtree.
+    pub fn tree_size(&self) -> usize {
+        self.zipper.focus.tree_size(0)
+    }
+
+    // Apply `f` to the zipper, which is left empty while it runs.
+    fn map_zipper(&mut self, f: impl FnOnce(ForestZipper) -> ForestZipper) {
+        let empty = ForestZipper {
+            before: Node::Empty,
+            focus: Node::Empty,
+            prev: Vec::new(),
+        };
+        self.recount_marks();
+        self.zipper = f(mem::replace(&mut self.zipper, empty));
+    }
+
+    /// Move to the position of the focused node's first child, which is the
+    /// end of its children if it has none.
+    ///
+    /// Return false without moving at the end of a sibling list.
+    pub fn descend(&mut self) -> bool {
+        if self.item().is_none() {
+            return false;
+        }
+        self.map_zipper(ForestZipper::focus_child);
+        self.index += 1;
+        true
+    }
+
+    /// Move to the parent of the focused position.
+    ///
+    /// Return false without moving if the position is among the roots.
+    pub fn ascend(&mut self) -> bool {
+        if self.zipper.prev.is_empty() {
+            return false;
+        }
+        self.index -= self.zipper.before.size() + 1;
+        self.map_zipper(ForestZipper::focus_parent);
+        true
+    }
+
+    /// Move to the position after the focused node's subtree.
+    ///
+    /// Return false without moving at the end of a sibling list.
+    pub fn next_sibling(&mut self) -> bool {
+        let size = self.tree_size();
+        if size == 0 {
+            return false;
+        }
+        self.map_zipper(ForestZipper::focus_sibling);
+        self.index += size;
+        true
+    }
+
+    /// Move to the previous sibling of the focused position.
+    ///
+    /// Return false without moving at the first sibling.
+    pub fn prev_sibling(&mut self) -> bool {
+        let size = self.zipper.before.last_tree_size();
+        if size == 0 {
+            return false;
+        }
+        self.map_zipper(ForestZipper::focus_prev_sibling);
+        self.index -= size;
+        true
+    }
+
+    /// Insert the trees of `forest` at the focused position, focusing on the
+    /// first inserted tree.
+    pub fn insert(&mut self, forest: Node) {
+        self.map_zipper(|zipper| zipper.insert(forest));
+    }
+
+    /// Remove the focused subtree and return it as a forest, focusing on the
+    /// position of its next sibling.
+    pub fn remove(&mut self) -> Node {
+        let mut removed = Tree::Empty;
+        self.map_zipper(|zipper| {
+            let (zipper, tree) = zipper.extract_tree();
+            removed = tree;
+            zipper
+        });
+        removed.into_forest()
+    }
+
+    /// Delete the focused node, putting its children in its place, and focus
+    /// on the position it had.
+    ///
+    /// Return the deleted item, or None at the end of a sibling list.
+    pub fn delete(&mut self) -> Option<Item> {
+        let mut deleted = None;
+        self.map_zipper(|zipper| match zipper.extract_tree() {
+            (zipper, Tree::Root { item, child }) => {
+                deleted = Some(item);
+                zipper.insert(child)
+            }
+            (zipper, Tree::Empty) => zipper,
+        });
+        deleted
+    }
+
+    /// Return the forest with the edits made, rebuilding the nodes above the
+    /// focused position.
+    pub fn finish(mut self) -> Node {
+        self.recount_marks();
+        self.zipper.restore()
+    }
+}
+
+impl<'a> Iterator for Trees<'a> {
+    type Item = (&'a Item, &'a Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Node::Node { item, child, right, .. } = self.stack.pop()? else {
+            return None;
+        };
+        let mut node = &**right;
+        while let Node::Node { left, .. } = node {
+            self.stack.push(node);
+            node = left;
+        }
+        Some((item, child))
+    }
+}
+
+/// Iterator type returning node labels/positions in pre-order.
+pub struct PreOrderIter<'a> {
+    stack: Vec<(Trees<'a>, NodeType)>,
+}
+
+impl<'a> Iterator for PreOrderIter<'a> {
+    type Item = (&'a Item, NodePosition);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (trees, node_type) = self.stack.last_mut()?;
+            let Some((item, child)) = trees.next() else {
+                self.stack.pop();
+                continue;
+            };
+            let is_last = trees.stack.is_empty();
+            let pos = NodePosition { node_type: *node_type, is_last };
+            if let NodeType::Child = node_type {
+                *node_type = NodeType::Sibling;
+            }
+            if child.size() > 0 {
+                self.stack.push((child.trees(), NodeType::Child));
+            }
+            return Some((item, pos));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Create a forest from a list of trees.
+    fn forest(trees: Vec<Tree>) -> Node {
+        let trees = trees.into_iter()
+            .filter_map(|tree| match tree {
+                Tree::Root { item, child } => Some((item, child)),
+                Tree::Empty => None,
+            })
+            .collect();
+        Node::from_trees(trees)
+    }
+
+    // Create a tree given the root `label` and list of child subtrees.
+    fn tree(label: &str, children: Vec<Tree>) -> Tree {
+        Tree::Root {
+            item: Item::new(NodeId(0), label.to_string()),
+            child: forest(children),
+        }
+    }
+
+    // Create a single-node tree.
+    fn leaf(label: &str) -> Tree {
+        Tree::Root {
+            item: Item::new(NodeId(0), label.to_string()),
+            child: Node::Empty,
+        }
+    }
+
+    #[test]
+    fn focus_empty_forest() {
+        let result_0 = Node::Empty.focus_node(0);
+        let result_1 = Node::Empty.focus_node(1);
+        let empty_zipper = ForestZipper {
+            before: Node::Empty,
+            focus: Node::Empty,
+            prev: Vec::new(),
+        };
+
+        assert_eq!(result_0, empty_zipper);
+        assert_eq!(result_1, empty_zipper);
     }
 
     #[test]
@@ -493,5 +1697,478 @@ mod tests {
         assert_eq!(zipper_a1.restore(), forest_a);
         assert_eq!(zipper_a2.restore(), forest_a);
     }
-}
 
+    #[test]
+    fn extract_and_insert_subtree() {
+        let forest_a = forest(vec![
+            leaf("0"),
+            tree("1", vec![
+                leaf("2"),
+                leaf("3"),
+            ]),
+            leaf("4"),
+        ]);
+        let rest_a = forest(vec![
+            leaf("0"),
+            leaf("4"),
+        ]);
+        let subtree_a = forest(vec![
+            tree("1", vec![
+                leaf("2"),
+                leaf("3"),
+            ]),
+        ]);
+        let after_a = forest(vec![
+            leaf("0"),
+            leaf("4"),
+            tree("1", vec![
+                leaf("2"),
+                leaf("3"),
+            ]),
+        ]);
+        let child_a = forest(vec![
+            tree("0", vec![
+                tree("1", vec![
+                    leaf("2"),
+                    leaf("3"),
+                ]),
+            ]),
+            leaf("4"),
+        ]);
+        let copy = forest_a.copy_tree(1);
+        let (rest, subtree) = forest_a.clone().extract(1);
+        let before = rest.clone().insert(1, copy.clone(), Placement::Before);
+        let after = rest.clone().insert(1, copy.clone(), Placement::After);
+        let child = rest.clone()
+            .insert(0, copy.clone(), Placement::FirstChild);
+
+        assert_eq!(copy, subtree_a);
+        assert_eq!(subtree, subtree_a);
+        assert_eq!(rest, rest_a);
+        assert_eq!(before, (forest_a, 1));
+        assert_eq!(after, (after_a, 2));
+        assert_eq!(child, (child_a, 1));
+    }
+
+    #[test]
+    fn wrap_sibling_range() {
+        let forest_a = forest(vec![
+            leaf("0"),
+            tree("1", vec![
+                leaf("2"),
+                leaf("3"),
+                leaf("4"),
+            ]),
+        ]);
+        let wrapped_a = forest(vec![
+            leaf("0"),
+            tree("1", vec![
+                tree("new", vec![
+                    leaf("2"),
+                    leaf("3"),
+                ]),
+                leaf("4"),
+            ]),
+        ]);
+        let zipper_a2 = forest_a.clone()
+            .focus_node(2)
+            .wrap(2, Item::new(NodeId(0), "new".to_string()));
+
+        assert_eq!(forest_a.sibling_count(2), 3);
+        assert!(forest_a.has_more_siblings(2, 2));
+        assert!(!forest_a.has_more_siblings(2, 3));
+        assert_eq!(forest_a.siblings_size(0, 2), 5);
+        assert_eq!(zipper_a2.focus.size(), 4);
+        assert_eq!(zipper_a2.restore(), wrapped_a);
+        let item = Item::new(NodeId(0), "new".to_string());
+
+        assert_eq!(forest_a.wrap(2, 2, item), wrapped_a);
+    }
+
+    #[test]
+    fn move_subtree_to_target() {
+        let forest_a = forest(vec![
+            tree("0", vec![
+                leaf("1"),
+            ]),
+            tree("2", vec![
+                leaf("3"),
+                leaf("4"),
+            ]),
+        ]);
+        let moved_a = forest(vec![
+            tree("2", vec![
+                leaf("3"),
+                leaf("4"),
+                tree("0", vec![
+                    leaf("1"),
+                ]),
+            ]),
+        ]);
+        let moved = forest_a.clone().move_to(0, 2, Placement::LastChild);
+        let refused = forest_a.clone().move_to(2, 4, Placement::FirstChild);
+
+        assert_eq!(moved, (moved_a, 3));
+        assert_eq!(refused, (forest_a, 2));
+    }
+
+    #[test]
+    fn duplicate_and_rewrite_subtree() {
+        let forest_a = forest(vec![
+            tree("a0", vec![
+                leaf("a1"),
+                leaf("1a"),
+            ]),
+            leaf("a2"),
+        ]);
+        let duplicated_a = forest(vec![
+            tree("a0", vec![
+                leaf("a1"),
+                leaf("1a"),
+            ]),
+            tree("b0", vec![
+                leaf("b1"),
+                leaf("1a"),
+            ]),
+            leaf("a2"),
+        ]);
+        let rewrite = |label: String| label.replacen('a', "b", 1);
+        let rewrite_prefix = |label: String| match label.starts_with('a') {
+            true => rewrite(label),
+            false => label,
+        };
+        let mut ids = IdGen::new(&forest_a);
+        let (result, i) = forest_a.duplicate(0, &mut ids, rewrite_prefix);
+        let copy_ids: Vec<NodeId> = result.ids().skip(3).take(3).collect();
+        let normalize = |root: Node| root.renumber(&mut IdGen::default());
+
+        assert_eq!(i, 3);
+        assert_eq!(copy_ids, vec![NodeId(1), NodeId(2), NodeId(3)]);
+        assert_eq!(normalize(result), normalize(duplicated_a));
+    }
+
+    #[test]
+    fn find_node_by_id() {
+        let forest_a = forest(vec![
+            tree("0", vec![
+                leaf("1"),
+            ]),
+            leaf("2"),
+        ])
+        .renumber(&mut IdGen::default());
+        let id = forest_a.find_id(0);
+        let (moved, _) = forest_a.move_to(0, 2, Placement::After);
+
+        assert_eq!(moved.find_index(id), Some(1));
+        assert_eq!(moved.find_index(NodeId(3)), None);
+    }
+
+    #[test]
+    fn jump_by_motion() {
+        let forest_a = forest(vec![
+            tree("0", vec![
+                tree("1", vec![
+                    leaf("2"),
+                ]),
+                leaf("3"),
+            ]),
+            leaf("4"),
+            tree("5", vec![
+                leaf("6"),
+            ]),
+        ]);
+        let jump = |index, motion| forest_a.jump(index, motion);
+
+        assert_eq!(jump(2, Motion::Parent), 1);
+        assert_eq!(jump(0, Motion::Parent), 0);
+        assert_eq!(jump(0, Motion::FirstChild), 1);
+        assert_eq!(jump(3, Motion::FirstChild), 3);
+        assert_eq!(jump(1, Motion::NextSibling), 3);
+        assert_eq!(jump(3, Motion::PrevSibling), 1);
+        assert_eq!(jump(1, Motion::PrevSibling), 1);
+        assert_eq!(jump(6, Motion::FirstRoot), 0);
+        assert_eq!(jump(2, Motion::LastRoot), 5);
+        assert_eq!(jump(3, Motion::NextAtDepth), 6);
+        assert_eq!(jump(2, Motion::NextAtDepth), 2);
+        assert_eq!(jump(7, Motion::Parent), 7);
+    }
+
+    #[test]
+    fn path_labels_to_node() {
+        let forest_a = forest(vec![
+            leaf("a"),
+            tree("b", vec![
+                tree("c", vec![
+                    leaf("d"),
+                ]),
+            ]),
+        ]);
+
+        assert_eq!(forest_a.path_labels(3), vec!["b", "c", "d"]);
+        assert_eq!(forest_a.path_labels(0), vec!["a"]);
+        assert!(forest_a.path_labels(4).is_empty());
+    }
+
+    #[test]
+    fn iterate_from_node() {
+        let forest_a = forest(vec![
+            tree("a", vec![
+                leaf("b"),
+                tree("c", vec![
+                    leaf("d"),
+                ]),
+            ]),
+            leaf("e"),
+        ]);
+        let position = |NodePosition { node_type, is_last }| {
+            let node_type = match node_type {
+                NodeType::Root => 'r',
+                NodeType::Child => 'c',
+                NodeType::Sibling => 's',
+            };
+            (node_type, is_last)
+        };
+        let rows = |iter: PreOrderIter| -> Vec<_> {
+            iter.map(|(item, pos)| (item.label.clone(), position(pos)))
+                .collect()
+        };
+
+        for i in 0..=forest_a.size() {
+            let skipped: Vec<_> = rows(forest_a.iter()).split_off(i);
+            assert_eq!(rows(forest_a.iter_from(i)), skipped);
+        }
+        let positions: Vec<_> = forest_a.path_positions(3)
+            .into_iter()
+            .map(position)
+            .collect();
+        assert_eq!(positions, vec![('r', false), ('s', true), ('c', true)]);
+    }
+
+    #[test]
+    fn hoist_and_unhoist_subtree() {
+        let forest_a = forest(vec![
+            leaf("0"),
+            tree("1", vec![
+                leaf("2"),
+                leaf("3"),
+            ]),
+        ]);
+        let children_a = forest(vec![
+            leaf("2"),
+            leaf("3"),
+        ]);
+        let edited_a = forest(vec![
+            leaf("0"),
+            tree("1", vec![
+                leaf("3"),
+            ]),
+        ]);
+        let (children, hoisted) = forest_a.clone().hoist(1);
+
+        assert_eq!(children, children_a);
+        assert_eq!(hoisted.label(), "1");
+        assert_eq!(hoisted.ids().len(), 2);
+        assert_eq!(hoisted.clone().unhoist(children), (forest_a, 1));
+        assert_eq!(hoisted.unhoist(children_a.delete(0)), (edited_a, 1));
+    }
+
+    #[test]
+    fn build_forest_from_outline_and_back() {
+        let forest_a = forest(vec![
+            tree("0", vec![
+                tree("1", vec![
+                    leaf("2"),
+                ]),
+                leaf("3"),
+            ]),
+            leaf("4"),
+        ]);
+        let outline = [(0, "0"), (1, "1"), (2, "2"), (1, "3"), (0, "4")];
+        let items = outline
+            .map(|(depth, label)| {
+                (depth, Item::new(NodeId(0), label.to_string()))
+            });
+
+        assert_eq!(Node::from_outline(items), forest_a);
+        assert_eq!(forest_a.outline(), outline.to_vec());
+    }
+
+    #[test]
+    fn delete_node_and_subtree() {
+        let forest_a = forest(vec![
+            tree("0", vec![
+                leaf("1"),
+            ]),
+            leaf("2"),
+        ]);
+        let node_deleted_a = forest(vec![
+            leaf("1"),
+            leaf("2"),
+        ]);
+        let tree_deleted_a = forest(vec![
+            leaf("2"),
+        ]);
+
+        assert_eq!(forest_a.tree_size(0), 2);
+        assert_eq!(forest_a.clone().delete(0), node_deleted_a);
+        assert_eq!(forest_a.delete_tree(0), tree_deleted_a);
+    }
+
+    #[test]
+    fn move_and_sort_marked_subtrees() {
+        let forest_a = forest(vec![
+            tree("0", vec![
+                leaf("c"),
+                leaf("b"),
+            ]),
+            leaf("3"),
+            tree("4", vec![
+                leaf("a"),
+            ]),
+        ]);
+        let moved_a = forest(vec![
+            leaf("3"),
+            tree("0", vec![
+                leaf("b"),
+                leaf("c"),
+            ]),
+            tree("4", vec![
+                leaf("a"),
+            ]),
+        ]);
+        let marked = forest_a
+            .set_mark(0, true)
+            .set_mark(2, true)
+            .set_mark(4, true);
+        let (moved, i) = marked.clone()
+            .move_marked(3, Placement::After);
+        let (rest, _) = marked.clone().extract_marked();
+
+        assert_eq!(marked.mark_count(), 3);
+        assert_eq!(marked.marked_trees(), vec![0..3, 4..6]);
+        assert_eq!(rest, forest(vec![leaf("3")]));
+        assert_eq!(i, 1);
+        assert_eq!(moved.mark_count(), 3);
+        assert_eq!(moved.sort_marked().clear_marks(), moved_a);
+        let tagged = marked.map_marked(&mut |item| {
+            Item { label: format!("{}!", item.label), ..item }
+        });
+        assert_eq!(tagged.outline(), vec![
+            (0, "0!"),
+            (1, "c"),
+            (1, "b!"),
+            (0, "3"),
+            (0, "4!"),
+            (1, "a"),
+        ]);
+        assert_eq!(tagged.clear_marks().mark_count(), 0);
+    }
+
+    #[test]
+    fn insert_into_empty_forest() {
+        let subtree = forest(vec![leaf("0")]);
+        let before = Node::Empty.insert(0, subtree.clone(), Placement::Before);
+        let after = Node::Empty.insert(0, subtree.clone(), Placement::After);
+
+        assert_eq!(before, (subtree.clone(), 0));
+        assert_eq!(after, (subtree, 0));
+    }
+
+    #[test]
+    fn move_and_edit_with_cursor() {
+        let forest_a = forest(vec![
+            leaf("0"),
+            tree("1", vec![
+                leaf("2"),
+                leaf("3"),
+            ]),
+            leaf("4"),
+        ]);
+        let result_a = forest(vec![
+            leaf("0"),
+            leaf("2"),
+            leaf("x"),
+            leaf("3"),
+        ]);
+        let label = |cursor: &Cursor| {
+            cursor.item().map(|item| item.label.clone())
+        };
+        let mut cursor = forest_a.cursor(2);
+
+        assert!(cursor.ascend());
+        assert_eq!((label(&cursor), cursor.index()), (Some("1".into()), 1));
+        assert!(!cursor.ascend());
+        assert!(cursor.descend() && cursor.next_sibling());
+        assert!(cursor.next_sibling());
+        assert_eq!((label(&cursor), cursor.index()), (None, 4));
+        assert!(!cursor.next_sibling() && !cursor.descend());
+        assert!(cursor.prev_sibling());
+        cursor.insert(forest(vec![leaf("w")]));
+        cursor.item_mut().unwrap().label = "x".to_string();
+        assert_eq!((label(&cursor), cursor.index()), (Some("x".into()), 3));
+        assert!(cursor.ascend() && cursor.next_sibling());
+        assert_eq!((label(&cursor), cursor.index()), (Some("4".into()), 5));
+        assert_eq!(cursor.remove(), forest(vec![leaf("4")]));
+        assert!(cursor.prev_sibling());
+        assert_eq!(cursor.delete().map(|item| item.label), Some("1".into()));
+        assert_eq!((label(&cursor), cursor.index()), (Some("2".into()), 1));
+        assert_eq!(cursor.finish(), result_a);
+    }
+
+    // Return the height of the binary trees of the `forest` if they are all
+    // balanced with correct sizes, marks, depths and heights, or None.
+    fn balanced_height(forest: &Node) -> Option<usize> {
+        let Node::Node {
+            item, child, left, right, size, marks, depth, height,
+        } = forest else {
+            return Some(0);
+        };
+        balanced_height(child)?;
+        let (left_height, right_height) =
+            (balanced_height(left)?, balanced_height(right)?);
+        let valid = left_height.abs_diff(right_height) <= 1
+            && *height == 1 + left_height.max(right_height)
+            && *size == left.size() + 1 + child.size() + right.size()
+            && *depth == left.depth()
+                .max(1 + child.depth())
+                .max(right.depth())
+            && *marks == left.mark_count()
+                + usize::from(item.marked)
+                + child.mark_count()
+                + right.mark_count();
+        valid.then_some(*height)
+    }
+
+    #[test]
+    fn balance_long_sibling_list() {
+        let count = 200_000;
+        let mut ids = IdGen::default();
+        let root = (0..count).rev()
+            .fold(Node::Empty, |root, i| {
+                root.prepend(Item::new(ids.next_id(), i.to_string()))
+            });
+        let middle = count / 2;
+        let (root, index) = root
+            .insert(middle, forest(vec![leaf("x")]), Placement::FirstChild);
+        let root = root.toggle_mark(0)
+            .toggle_mark(middle - 1)
+            .delete(middle - 1);
+        let (root, _) = root.move_forward(0);
+
+        assert!(balanced_height(&root).is_some_and(|height| height <= 25));
+        assert_eq!(index, middle + 1);
+        assert_eq!(root.mark_count(), 1);
+        assert_eq!(root.find_label(middle), "x");
+        assert_eq!(root.find_label(0), "1");
+        assert_eq!(root.find_label(1), "0");
+        assert_eq!(root.jump(middle - 1, Motion::NextSibling), middle + 1);
+        assert_eq!(root.jump(0, Motion::LastRoot), count - 1);
+        assert_eq!(root.sibling_count(middle + 1), count - middle - 1);
+
+        let last = root.size() - 1;
+        let (root, _) =
+            root.insert(last, forest(vec![leaf("y")]), Placement::FirstChild);
+        assert_eq!(root.jump(middle, Motion::NextAtDepth), last + 1);
+        assert_eq!(root.jump(last + 1, Motion::NextAtDepth), last + 1);
+    }
+}
@@ -0,0 +1,310 @@
+//! Data files storing a forest, and text export.
+
+use std::io::{self, ErrorKind};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    forest::{IdGen, Item, Node},
+    storage::Storage,
+};
+
+// Identifies a versioned data file, distinguishing it from legacy files which
+// contain only a serialized forest.
+const MAGIC: &[u8] = b"ELMLOG";
+const VERSION: u32 = 3;
+// Indentation of each outline level in exported text.
+const INDENT: &str = "  ";
+
+// Borrowed contents of a data file, used for serialization.
+#[derive(Serialize)]
+struct DataFileRef<'a> {
+    version: u32,
+    root: &'a Node,
+    ids: &'a IdGen,
+    cursor: usize,
+}
+
+// Contents of a data file, used for deserialization.
+#[derive(Deserialize)]
+struct DataFile {
+    _version: u32,
+    root: Node,
+    ids: IdGen,
+    cursor: usize,
+}
+
+// Contents of a version 2 data file, written before sibling lists were
+// balanced.
+#[derive(Deserialize)]
+struct DataFileV2 {
+    _version: u32,
+    root: NodeV2,
+    ids: IdGen,
+    cursor: usize,
+}
+
+// Contents of a version 1 data file, written before the cursor was saved.
+#[derive(Deserialize)]
+struct DataFileV1 {
+    _version: u32,
+    root: NodeV2,
+    ids: IdGen,
+}
+
+// Node layout of version 1 and 2 data files, a left-child right-sibling
+// binary tree.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+enum NodeV2 {
+    Empty,
+    Node {
+        item: Item,
+        child: Box<NodeV2>,
+        sibling: Box<NodeV2>,
+        // Recomputed on conversion.
+        #[allow(dead_code)]
+        size: usize,
+    },
+}
+
+// Node layout of data files written before node IDs were introduced.
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+enum LegacyNode {
+    Empty,
+    Node {
+        label: String,
+        child: Box<LegacyNode>,
+        sibling: Box<LegacyNode>,
+        // Recomputed on conversion.
+        #[allow(dead_code)]
+        size: usize,
+    },
+}
+
+impl NodeV2 {
+    // Convert the forest into a Node forest.
+    fn into_node(self) -> Node {
+        let mut items = Vec::new();
+        self.push_items(0, &mut items);
+        Node::from_outline(items)
+    }
+
+    // Append the items of the sibling trees at `depth` to `items`.
+    fn push_items(self, depth: usize, items: &mut Vec<(usize, Item)>) {
+        let mut focus = self;
+        while let NodeV2::Node { item, child, sibling, .. } = focus {
+            items.push((depth, item));
+            child.push_items(depth + 1, items);
+            focus = *sibling;
+        }
+    }
+}
+
+impl LegacyNode {
+    // Convert the legacy forest into a Node forest with IDs numbered from 0
+    // in pre-order.
+    fn into_node(self) -> Node {
+        let mut ids = IdGen::default();
+        let mut items = Vec::new();
+        self.push_items(0, &mut ids, &mut items);
+        Node::from_outline(items)
+    }
+
+    // Append the items of the sibling trees at `depth` to `items`, assigning
+    // IDs from `ids` in pre-order.
+    fn push_items(
+        self,
+        depth: usize,
+        ids: &mut IdGen,
+        items: &mut Vec<(usize, Item)>,
+    ) {
+        let mut focus = self;
+        while let LegacyNode::Node { label, child, sibling, .. } = focus {
+            items.push((depth, Item::new(ids.next_id(), label)));
+            child.push_items(depth + 1, ids, items);
+            focus = *sibling;
+        }
+    }
+}
+
+/// Serialize the forest rooted at `root`, its `ids` and the `cursor` index
+/// into data file bytes.
+pub fn serialize(
+    root: &Node,
+    ids: &IdGen,
+    cursor: usize,
+) -> bincode::Result<Vec<u8>> {
+    let data_file = DataFileRef { version: VERSION, root, ids, cursor };
+    let mut bytes = MAGIC.to_vec();
+    bincode::serialize_into(&mut bytes, &data_file)?;
+    Ok(bytes)
+}
+
+/// Deserialize a forest, its ID generator and the cursor index from data file
+/// `bytes`.
+///
+/// Legacy files without IDs are converted, numbering nodes in pre-order.
+/// Files without a saved cursor place it at the first item.
+pub fn deserialize(bytes: &[u8]) -> bincode::Result<(Node, IdGen, usize)> {
+    let Some(data) = bytes.strip_prefix(MAGIC) else {
+        let root = bincode::deserialize::<LegacyNode>(bytes)?.into_node();
+        let ids = IdGen::new(&root);
+        return Ok((root, ids, 0));
+    };
+    match bincode::deserialize::<u32>(data)? {
+        1 => {
+            let DataFileV1 { root, ids, .. } = bincode::deserialize(data)?;
+            Ok((root.into_node(), ids, 0))
+        }
+        2 => {
+            let DataFileV2 { root, ids, cursor, .. } =
+                bincode::deserialize(data)?;
+            Ok((root.into_node(), ids, cursor))
+        }
+        VERSION => {
+            let DataFile { root, ids, cursor, .. } =
+                bincode::deserialize(data)?;
+            Ok((root, ids, cursor))
+        }
+        version => {
+            let message = format!("Unsupported data file version {version}");
+            Err(Box::new(bincode::ErrorKind::Custom(message)))
+        }
+    }
+}
+
+/// Load a forest, its ID generator and the cursor index from the data file
+/// `name` in the `storage`.
+pub fn load(
+    storage: &dyn Storage,
+    name: &str,
+) -> io::Result<(Node, IdGen, usize)> {
+    let bytes = storage.read(name)?;
+    deserialize(&bytes).map_err(|error| {
+        io::Error::new(ErrorKind::InvalidData, error)
+    })
+}
+
+/// Save the forest rooted at `root`, its `ids` and the `cursor` index to the
+/// data file `name` in the `storage`, replacing its contents.
+pub fn save(
+    storage: &dyn Storage,
+    name: &str,
+    root: &Node,
+    ids: &IdGen,
+    cursor: usize,
+) -> io::Result<()> {
+    let bytes = serialize(root, ids, cursor).map_err(|error| {
+        io::Error::new(ErrorKind::InvalidData, error)
+    })?;
+    storage.write(name, &bytes)
+}
+
+/// Text formats the forest can be exported to.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExportFormat {
+    Markdown,
+    Text,
+}
+
+/// Return the forest rooted at `root` as a nested Markdown list, or as plain
+/// text with each label indented by its depth.
+pub fn export(root: &Node, format: ExportFormat) -> String {
+    root.outline()
+        .into_iter()
+        .map(|(depth, label)| {
+            let indent = INDENT.repeat(depth);
+            match format {
+                ExportFormat::Markdown => format!("{indent}- {label}\n"),
+                ExportFormat::Text => format!("{indent}{label}\n"),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_data_file() {
+        let mut ids = IdGen::default();
+        let root = Node::Empty
+            .prepend(Item::new(ids.next_id(), "b".to_string()))
+            .prepend(Item::new(ids.next_id(), "a".to_string()));
+        let bytes = serialize(&root, &ids, 1).unwrap();
+        let (result, mut result_ids, cursor) = deserialize(&bytes).unwrap();
+
+        assert_eq!(result, root);
+        assert_eq!(cursor, 1);
+        assert_eq!(result_ids.next_id(), ids.next_id());
+    }
+
+    #[test]
+    fn export_outline() {
+        let mut ids = IdGen::default();
+        let root = Node::from_outline([(0, "a"), (0, "b"), (1, "c")]
+            .map(|(depth, label)| {
+                (depth, Item::new(ids.next_id(), label.to_string()))
+            }));
+
+        assert_eq!(export(&root, ExportFormat::Markdown), "- a\n- b\n  - c\n");
+        assert_eq!(export(&root, ExportFormat::Text), "a\nb\n  c\n");
+    }
+
+    #[test]
+    fn convert_legacy_file() {
+        let legacy_leaf = |label: &str, sibling| LegacyNode::Node {
+            label: label.to_string(),
+            child: Box::new(LegacyNode::Empty),
+            size: 1 + match &sibling {
+                LegacyNode::Node { size, .. } => *size,
+                LegacyNode::Empty => 0,
+            },
+            sibling: Box::new(sibling),
+        };
+        let legacy = legacy_leaf("a", legacy_leaf("b", LegacyNode::Empty));
+        let bytes = bincode::serialize(&legacy).unwrap();
+        let (root, mut ids, cursor) = deserialize(&bytes).unwrap();
+        let labels: Vec<&str> = root.iter()
+            .map(|(item, _)| item.label.as_str())
+            .collect();
+
+        assert_eq!(labels, vec!["a", "b"]);
+        assert_eq!(cursor, 0);
+        assert_eq!(root.find_index(root.find_id(1)), Some(1));
+        assert_eq!(ids.next_id(), IdGen::new(&root).next_id());
+    }
+
+    #[test]
+    fn convert_version_2_file() {
+        let mut ids = IdGen::default();
+        let node_v2 = |item, child, sibling: NodeV2| NodeV2::Node {
+            item,
+            child: Box::new(child),
+            size: 0,
+            sibling: Box::new(sibling),
+        };
+        let b = node_v2(
+            Item::new(ids.next_id(), "b".to_string()),
+            NodeV2::Empty,
+            NodeV2::Empty,
+        );
+        let c = node_v2(
+            Item::new(ids.next_id(), "c".to_string()),
+            NodeV2::Empty,
+            NodeV2::Empty,
+        );
+        let a = node_v2(Item::new(ids.next_id(), "a".to_string()), b, c);
+        let mut bytes = MAGIC.to_vec();
+        bincode::serialize_into(&mut bytes, &(2u32, &a, &ids, 1usize))
+            .unwrap();
+        let (root, mut result_ids, cursor) = deserialize(&bytes).unwrap();
+
+        assert_eq!(root.outline(), vec![(0, "a"), (1, "b"), (0, "c")]);
+        assert_eq!(cursor, 1);
+        assert_eq!(result_ids.next_id(), ids.next_id());
+    }
+}
@@ -1,15 +1,16 @@
 use std::{
-    fs::{self, File, OpenOptions},
-    io::{Read, Result},
-    path::{Path, PathBuf},
+    io::{self, ErrorKind, Result},
+    path::Path,
 };
 
-use fs2::FileExt;
-
 use crate::{
+    config,
+    editor::LineEditor,
     forest::Node,
+    format::{self, ExportFormat},
     message::Command,
     model::{
+        CommandLineState,
         FilenameAction,
         FilenameState,
         FilenameStatus,
@@ -18,47 +19,30 @@ use crate::{
         PostSaveAction,
         SessionState,
     },
+    storage::{FileLock, Storage},
     util,
 };
 
-const APP_DIR: &str = "sieve-selector";
-
-/// The `name` and `path` of a file.
-pub struct FileEntry {
-    name: String,
-    path: PathBuf,
-}
-
-/// List of `files` in the app directory and `index` of the current selection.
+/// List of data `files` in the storage and `index` of the current selection.
 pub struct LoadState {
-    files: Vec<FileEntry>,
+    files: Vec<String>,
     index: usize,
 }
 
-/// A file locked for exclusive data access.
+/// A data file locked for exclusive data access.
 ///
-/// The File is only stored to keep the lock active.
+/// The lock is only stored to keep it active. The `cursor` is the index saved
+/// in the file, within the full forest.
 pub struct OpenDataFile {
     name: String,
-    path: PathBuf,
-    _file: File,
+    _lock: Box<dyn FileLock>,
     changed: bool,
-}
-
-impl FileEntry {
-    fn rename(&self, filename: &str) -> Result<Self> {
-        let path = app_dir_path().join(filename);
-        fs::rename(&self.path, &path)?;
-        Ok(FileEntry {
-            name: filename.to_string(),
-            path,
-        })
-    }
+    cursor: usize,
 }
 
 impl LoadState {
-    /// Move the selected FileEntry.
-    pub fn move_file_entry(mut self) -> FileEntry {
+    /// Move the selected filename.
+    pub fn move_filename(mut self) -> String {
         self.files.swap_remove(self.index)
     }
 
@@ -88,14 +72,14 @@ impl LoadState {
 
     /// Return the selected filename.
     pub fn filename(&self) -> &str {
-        &self.files[self.index].name
+        &self.files[self.index]
     }
 
     /// Iterate over the filenames.
     pub fn filename_iter(&self) -> impl Iterator<Item = &str> {
         self.files
             .iter()
-            .map(|f| f.name.as_str())
+            .map(String::as_str)
     }
 
     /// Return the total number of files.
@@ -113,18 +97,19 @@ impl LoadState {
         self
     }
 
-    // Rename the selected file.
-    fn rename(&mut self, filename: &str) -> Result<()> {
-        let i = self.index;
-        self.files[i] = self.files[i].rename(filename)?;
+    // Rename the selected file in the `storage`.
+    fn rename(&mut self, filename: &str, storage: &dyn Storage) -> Result<()> {
+        storage.rename(&self.files[self.index], filename)?;
+        self.files[self.index] = filename.to_string();
         Ok(())
     }
 
-    // Delete the currently selected file and remove it from the list.
+    // Delete the currently selected file from the `storage` and remove it from
+    // the list.
     // Return None if there are no files left.
-    fn delete(mut self) -> Option<Self> {
-        let entry = self.files.remove(self.index);
-        fs::remove_file(entry.path)
+    fn delete(mut self, storage: &dyn Storage) -> Option<Self> {
+        let name = self.files.remove(self.index);
+        storage.delete(&name)
             .expect("Failed to delete file");
         if self.files.is_empty() {
             return None;
@@ -148,144 +133,174 @@ impl OpenDataFile {
     pub fn get_name(&self) -> &str {
         &self.name
     }
-}
 
-// Return the application directory path, creating any missing directories.
-fn app_dir_path() -> PathBuf {
-    let data_dir = dirs::data_dir()
-        .expect("Failed to identify data directory");
-    let path = data_dir.join(APP_DIR);
-    fs::create_dir_all(&path)
-        .expect("Failed to create data directory");
-    path
+    pub fn get_cursor(&self) -> usize {
+        self.cursor
+    }
 }
 
-// Return the LoadState if there is a least one data file.
-fn get_load_state() -> Option<LoadState> {
-    let files: Vec<FileEntry> = fs::read_dir(app_dir_path())
-        .expect("Unable to read app directory")
-        .filter_map(Result::ok)
-        .map(|entry| {
-            let name = entry
-                .file_name()
-                .to_string_lossy()
-                .into_owned();
-            let path = entry.path();
-            FileEntry { name, path }
-        })
-        .collect();
+// Return the LoadState if there is a least one data file in the `storage`,
+// listing the files by name.
+fn get_load_state(storage: &dyn Storage) -> Option<LoadState> {
+    let files = storage.list()
+        .expect("Unable to list data files");
     match files.len() {
         0 => None,
         _ => Some(LoadState { files, index: 0 }),
     }
 }
 
-// Lock the `file` for exclusive data access.
-fn lock(file: &File) {
-    file.try_lock_exclusive()
+// Open and lock the data file `name` in the `storage`, whose saved cursor is
+// at `cursor`.
+fn open_data_file(
+    name: String,
+    storage: &dyn Storage,
+    cursor: usize,
+) -> Result<OpenDataFile> {
+    let lock = storage.lock(&name)?;
+    Ok(OpenDataFile {
+        name,
+        _lock: lock,
+        changed: false,
+        cursor,
+    })
+}
+
+// Initialize a session's state from the data file `name` in the `storage`.
+fn init_session_state(
+    name: String,
+    register: Node,
+    storage: &dyn Storage,
+) -> SessionState {
+    let mut open_file = open_data_file(name, storage, 0)
         .expect("File is currently locked");
+    let (root, ids, cursor) = format::load(storage, &open_file.name)
+        .expect("Failed to load file");
+    open_file.cursor = cursor;
+    SessionState {
+        cursor,
+        maybe_file: Some(open_file),
+        register,
+        ..SessionState::new(root, ids)
+    }
 }
 
-// Load a forest from a serialized data `file`.
-fn load_forest(mut file: &File) -> Node {
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)
-        .expect("Failed to read file");
-    bincode::deserialize(&buffer)
-        .expect("Failed to deserialize data")
+// Write the full forest, ID generator and cursor of the session `state` to the
+// data file `name` in the `storage`.
+// Return the cursor index written.
+fn write_to_file(
+    state: &SessionState,
+    name: &str,
+    storage: &dyn Storage,
+) -> Result<usize> {
+    let (root, cursor) = state.full_forest();
+    format::save(storage, name, &root, &state.ids, cursor)?;
+    Ok(cursor)
 }
 
-// Initialize a session's state using the `path` to a data file.
-fn init_session_state(file_entry: FileEntry) -> SessionState {
-    let FileEntry { name, path } = file_entry;
-    let file = OpenOptions::new()
-        .read(true)
-        .open(&path)
-        .expect("Failed to open file");
-    lock(&file);
-    let root = load_forest(&file);
-    let open_file = OpenDataFile {
-        name,
-        path,
-        _file: file,
-        changed: false,
+// Save the current session `state` to its data file in the `storage`, if
+// present.
+//
+// The written file replaces the old one, so the old lock is kept until the
+// new file is locked.
+fn save(state: &mut SessionState, storage: &dyn Storage) -> Result<()> {
+    let Some(open_file) = &state.maybe_file else {
+        return Ok(());
     };
-    SessionState { root, maybe_file: Some(open_file) }
+    let name = open_file.name.clone();
+    let cursor = write_to_file(state, &name, storage)?;
+    state.maybe_file = Some(open_data_file(name, storage, cursor)?);
+    Ok(())
 }
 
-// Check whether `filename` exists in the app directory.
-fn filename_exists(filename: &str) -> bool {
-    let path = app_dir_path().join(filename);
-    path.exists()
+// Rename the open data file of the session `state` to `filename` in the
+// `storage`.
+fn rename_open(
+    state: &mut SessionState,
+    filename: &str,
+    storage: &dyn Storage,
+) -> Result<()> {
+    if storage.exists(filename) {
+        return Err(io::Error::new(ErrorKind::AlreadyExists, "File Exists"));
+    }
+    if let Some(open_file) = &mut state.maybe_file {
+        storage.rename(&open_file.name, filename)?;
+        open_file.name = filename.to_string();
+    }
+    Ok(())
 }
 
-// Return the root Node and data file path (if present) from the session state.
-// The locked File is implicitly dropped to unlock it.
-fn unlock_state(state: SessionState) -> (Node, Option<PathBuf>) {
-    let SessionState { root, maybe_file } = state;
-    let maybe_path = maybe_file
-        .map(|open_file| open_file.path);
-    (root, maybe_path)
+// Write the full forest of the session `state` to the exported file at `path`
+// in the `storage`, in the export `format`.
+fn export(
+    state: &SessionState,
+    format: ExportFormat,
+    path: &Path,
+    storage: &dyn Storage,
+) -> Result<()> {
+    let (root, _) = state.full_forest();
+    storage.export(path, format::export(&root, format).as_bytes())
 }
 
-// Set whether the file's permissions are read only.
-fn set_read_only(path: &Path, read_only: bool) {
-    let mut permissions = File::open(path)
-        .expect("Failed to open file")
-        .metadata()
-        .expect("Failed to extract metadata")
-        .permissions();
-    permissions.set_readonly(read_only);
-    fs::set_permissions(path, permissions)
-        .expect("Failed to set file permissions");
+// Return the Model after running a command line command with the `result`,
+// showing the error on the `command_line` if it failed.
+fn command_line_result(
+    state: SessionState,
+    command_line: CommandLineState,
+    result: Result<()>,
+) -> Model {
+    let mode = match result {
+        Ok(()) => Mode::Normal,
+        Err(error) => command_line.info(error.to_string()).into_mode(),
+    };
+    Model { state, mode }
 }
 
-// Write the forest rooted at `root` to an existing file at `path`.
-fn write_to_file(root: &Node, path: &Path) {
-    set_read_only(path, false);
-    let file = OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(path)
-        .expect("Failed to write to file");
-    lock(&file);
-    bincode::serialize_into(&file, root)
-        .expect("Failed to serialize data");
-    set_read_only(path, true);
+// Return the `model` showing the `error` of a command on the command line.
+fn show_error(model: Model, error: String) -> Model {
+    let command_line = CommandLineState {
+        input: LineEditor::default(),
+        info: Some(error),
+    };
+    Model { state: model.state, mode: command_line.into_mode() }
 }
 
-// Save the current session `state`.
-fn save(state: SessionState) {
-    let (root, maybe_path) = unlock_state(state);
-    if let Some(path) = maybe_path {
-        write_to_file(&root, &path);
+// Perform the `action` after saving the session `state`.
+fn after_save(
+    action: PostSaveAction,
+    state: SessionState,
+    storage: &dyn Storage,
+) -> Option<Model> {
+    match action {
+        PostSaveAction::Load => {
+            execute_command(Command::Load(state.register), storage)
+        }
+        PostSaveAction::Quit => None,
+        PostSaveAction::Continue => Some(Model { state, mode: Mode::Normal }),
     }
 }
 
-// Save the forest rooted at `root` to the file `filename`.
-fn save_new(root: &Node, filename: &str) -> Result<()> {
-    let path = app_dir_path().join(filename);
-    File::create_new(&path)?;
-    write_to_file(root, &path);
-    Ok(())
-}
-
-/// Execute `command` and return the updated Model.
-pub fn execute_command(command: Command) -> Option<Model> {
+/// Execute `command` with the files in the `storage` and return the updated
+/// Model.
+pub fn execute_command(
+    command: Command,
+    storage: &dyn Storage,
+) -> Option<Model> {
     let model = match command {
         Command::None(model) => model,
-        Command::Load => match get_load_state() {
-            Some(load_state) => Model::load(load_state),
-            None => Model::default(),
+        Command::Load(register) => match get_load_state(storage) {
+            Some(load_state) => Model::load(load_state, register),
+            None => Model::default(register),
         }
-        Command::InitSession(file_entry) => {
+        Command::InitSession(filename, register) => {
             Model {
-                state: init_session_state(file_entry),
+                state: init_session_state(filename, register, storage),
                 mode: Mode::Normal,
             }
         }
         Command::CheckFileExists(state, filename_state) => {
-            let status = match filename_exists(filename_state.input()) {
+            let filename = filename_state.input();
+            let status = match storage.exists(filename) {
                 true => FilenameStatus::Exists,
                 false => FilenameStatus::Valid,
             };
@@ -293,9 +308,9 @@ pub fn execute_command(command: Command) -> Option<Model> {
             Model { state, mode }
         }
         Command::Rename(state, filename, mut load_state) => {
-            let status = match filename_exists(&filename) {
+            let status = match storage.exists(&filename) {
                 true => FilenameStatus::Exists,
-                false => match load_state.rename(&filename) {
+                false => match load_state.rename(&filename, storage) {
                     Err(_) => FilenameStatus::Invalid,
                     Ok(()) => {
                         let mode = Mode::Load(load_state);
@@ -304,45 +319,138 @@ pub fn execute_command(command: Command) -> Option<Model> {
                 }
             };
             let mode = FilenameState {
-                input: filename,
+                input: LineEditor::new(filename),
                 action: FilenameAction::Rename(load_state),
                 status,
             }
             .into_mode();
             Model { state, mode }
         }
-        Command::SaveNew(state, filename, post_save) => {
-            let status = match filename_exists(&filename) {
+        Command::SaveNew(mut state, filename, post_save) => {
+            let status = match storage.exists(&filename) {
                 true => FilenameStatus::Exists,
-                false => match save_new(&state.root, &filename) {
+                false => match write_to_file(&state, &filename, storage) {
                     Err(_) => FilenameStatus::Invalid,
-                    Ok(()) => return match post_save {
-                        PostSaveAction::Load => execute_command(Command::Load),
-                        PostSaveAction::Quit => None,
+                    Ok(cursor) => {
+                        let open_file =
+                            open_data_file(filename, storage, cursor)
+                                .expect("File is currently locked");
+                        state.maybe_file = Some(open_file);
+                        return after_save(post_save, state, storage);
                     }
                 }
             };
             let mode = FilenameState {
-                input: filename,
+                input: LineEditor::new(filename),
                 action: FilenameAction::SaveNew(post_save),
                 status,
             }
             .into_mode();
             Model { state, mode }
         }
-        Command::Save(state, action) => {
-            save(state);
-            return match action {
-                PostSaveAction::Load => execute_command(Command::Load),
-                PostSaveAction::Quit => None,
+        Command::Save(mut state, action) => {
+            match save(&mut state, storage) {
+                Ok(()) => return after_save(action, state, storage),
+                Err(error) => {
+                    let model = Model { state, mode: Mode::Normal };
+                    show_error(model, error.to_string())
+                }
+            }
+        }
+        Command::RenameOpen(mut state, command_line, filename) => {
+            let result = rename_open(&mut state, &filename, storage);
+            command_line_result(state, command_line, result)
+        }
+        Command::Export(state, command_line, format, path) => {
+            let result = export(&state, format, &path, storage);
+            command_line_result(state, command_line, result)
+        }
+        Command::DeleteFile(load_state, register) => {
+            match load_state.delete(storage) {
+                Some(load_state) => Model::load(load_state, register),
+                None => Model::default(register),
+            }
+        }
+        Command::Clipboard(model, text) => {
+            storage
+                .copy_to_clipboard(&text)
+                .expect("Failed to copy to clipboard");
+            model
+        }
+        Command::SaveMacro(model, register, keys) => {
+            match config::save_macro(register, &keys, storage) {
+                Ok(()) => model,
+                Err(error) => show_error(model, error),
             }
         }
-        Command::DeleteFile(load_state) => match load_state.delete() {
-            Some(load_state) => Model::load(load_state),
-            None => Model::default(),
+        Command::PlayMacro(model, register, times) => {
+            match config::load_macro(register, storage) {
+                Ok(keys) => {
+                    let Model { state, mode } = model;
+                    Model { state: state.play(&keys, times), mode }
+                }
+                Err(error) => show_error(model, error),
+            }
         }
         Command::Quit => return None,
     };
     Some(model)
 }
 
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        forest::Item,
+        message::{Message, NormalMsg},
+        storage::MemoryStorage,
+        update,
+    };
+
+    use super::*;
+
+    #[test]
+    fn execute_file_commands_in_memory() {
+        let storage = MemoryStorage::new();
+        let load = || execute_command(Command::Load(Node::Empty), &storage);
+        assert!(matches!(load().unwrap().mode, Mode::Confirm(_)));
+
+        let mut state = Model::default(Node::Empty).state;
+        let id = state.ids.next_id();
+        state.root = Node::Empty.prepend(Item::new(id, "a".to_string()));
+        let post_save = PostSaveAction::Continue;
+        let command = Command::SaveNew(state, "notes".to_string(), post_save);
+        let model = execute_command(command, &storage).unwrap();
+        assert_eq!(model.get_filename(), Some("notes"));
+        assert!(storage.lock("notes").is_err());
+        let command = Command::Save(model.state, PostSaveAction::Continue);
+        let model = execute_command(command, &storage).unwrap();
+        assert!(matches!(model.mode, Mode::Normal));
+        assert!(storage.lock("notes").is_err());
+        let path = PathBuf::from("notes.txt");
+        let command_line = CommandLineState {
+            input: LineEditor::default(),
+            info: None,
+        };
+        let format = ExportFormat::Text;
+        let command =
+            Command::Export(model.state, command_line, format, path.clone());
+        drop(execute_command(command, &storage));
+        assert_eq!(storage.exported(&path).unwrap(), b"a\n");
+
+        let Some(Model { mode: Mode::Load(load_state), .. }) = load() else {
+            panic!("Expected Load mode");
+        };
+        assert_eq!(load_state.filename(), "notes");
+        let command = Command::InitSession("notes".to_string(), Node::Empty);
+        let model = execute_command(command, &storage).unwrap();
+        assert_eq!(model.state.root.outline(), vec![(0, "a")]);
+        let quit = Message::Normal(NormalMsg::Quit);
+        assert!(matches!(update::update(quit, model.state), Command::Quit));
+
+        let command = Command::DeleteFile(load_state, Node::Empty);
+        execute_command(command, &storage);
+        assert_eq!(storage.list().unwrap(), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,960 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+use crate::{
+    forest::{Motion, Placement},
+    message::{
+        BulkMsg,
+        HelpMsg,
+        InputEdit,
+        InputMsg,
+        LoadMsg,
+        MacroMsg,
+        MoveMsg,
+        NormalMsg,
+        SaveMsg,
+        SelectedMsg,
+        SelectMsg,
+        TargetMsg,
+        WrapMsg,
+    },
+    model::Mode,
+};
+
+/// A key together with its modifiers.
+///
+/// Shift is not kept for character keys, as it is given by their case, and is
+/// always kept for Shift+Tab.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers = modifiers
+            & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        match code {
+            KeyCode::Char(_) => modifiers.remove(KeyModifiers::SHIFT),
+            KeyCode::BackTab => modifiers.insert(KeyModifiers::SHIFT),
+            _ => (),
+        }
+        KeyBinding { code, modifiers }
+    }
+
+    /// Return whether the key types a character in text input.
+    pub fn is_typed(&self) -> bool {
+        let ctrl = self.modifiers.contains(KeyModifiers::CONTROL);
+        matches!(self.code, KeyCode::Char(_)) && !ctrl
+    }
+
+    /// Return the key event of pressing the bound key.
+    pub fn to_event(self) -> KeyEvent {
+        KeyEvent::new(self.code, self.modifiers)
+    }
+
+    /// Parse a key such as "j", "J", "enter" or "ctrl+w".
+    ///
+    /// Shift with a letter gives the uppercase letter, and with tab gives
+    /// Shift+Tab. Other characters are not accepted with shift, as the
+    /// character typed depends on the keyboard layout.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = text;
+        while let Some((prefix, rest)) = key.split_once('+') {
+            modifiers |= match prefix.to_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => break,
+            };
+            key = rest;
+        }
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "delete" => KeyCode::Delete,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => KeyCode::F(name.strip_prefix('f')?.parse().ok()?),
+            }
+        };
+        let code = match (code, modifiers.contains(KeyModifiers::SHIFT)) {
+            (KeyCode::Char(c), true) if c.is_alphabetic() => {
+                let mut upper = c.to_uppercase();
+                match (upper.next(), upper.next()) {
+                    (Some(upper), None) => KeyCode::Char(upper),
+                    _ => return None,
+                }
+            }
+            (KeyCode::Char(_), true) => return None,
+            (KeyCode::Tab, true) => KeyCode::BackTab,
+            (code, _) => code,
+        };
+        Some(KeyBinding::new(code, modifiers))
+    }
+
+    /// Return the key as written in the config file, e.g. "ctrl+w", if it can
+    /// be parsed back.
+    pub fn name(&self) -> Option<String> {
+        let mut name = String::new();
+        for (modifier, prefix) in [
+            (KeyModifiers::CONTROL, "ctrl+"),
+            (KeyModifiers::ALT, "alt+"),
+            (KeyModifiers::SHIFT, "shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                name.push_str(prefix);
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => name.push_str("space"),
+            KeyCode::Char(c) => name.push(c),
+            KeyCode::Enter => name.push_str("enter"),
+            KeyCode::Esc => name.push_str("esc"),
+            KeyCode::Tab | KeyCode::BackTab => name.push_str("tab"),
+            KeyCode::Backspace => name.push_str("backspace"),
+            KeyCode::Delete => name.push_str("delete"),
+            KeyCode::Up => name.push_str("up"),
+            KeyCode::Down => name.push_str("down"),
+            KeyCode::Left => name.push_str("left"),
+            KeyCode::Right => name.push_str("right"),
+            KeyCode::Home => name.push_str("home"),
+            KeyCode::End => name.push_str("end"),
+            KeyCode::PageUp => name.push_str("pageup"),
+            KeyCode::PageDown => name.push_str("pagedown"),
+            KeyCode::F(n) => name.push_str(&format!("f{n}")),
+            _ => return None,
+        }
+        Some(name)
+    }
+}
+
+impl From<KeyEvent> for KeyBinding {
+    fn from(key: KeyEvent) -> Self {
+        KeyBinding::new(key.code, key.modifiers)
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    // Format the key as shown on the command bar, e.g. "J", "⇧J" or "↓".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "⇧")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) if c.is_uppercase() => write!(f, "⇧{c}"),
+            KeyCode::Char(c) => write!(f, "{}", c.to_uppercase()),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Tab | KeyCode::BackTab => write!(f, "Tab"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Del"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// Keys bound to an action in the config file, either one or a list.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Keys {
+    fn as_slice(&self) -> &[String] {
+        match self {
+            Keys::One(key) => std::slice::from_ref(key),
+            Keys::Many(keys) => keys,
+        }
+    }
+}
+
+// An action of a mode: its name in the config file, the message it sends, its
+// default keys and its description in the help.
+type Action<T> = (&'static str, T, &'static [&'static str], &'static str);
+
+/// A row of the help: the keys bound to an action, its name in the config
+/// file and its description.
+pub struct HelpRow {
+    pub keys: String,
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// The keys bound to each action of a mode.
+pub struct ModeKeys<T: 'static> {
+    actions: &'static [Action<T>],
+    keys: Vec<Vec<KeyBinding>>,
+}
+
+impl<T: Clone + PartialEq> ModeKeys<T> {
+    // Bind the default keys of the `actions`.
+    fn new(actions: &'static [Action<T>]) -> Self {
+        let keys = actions.iter()
+            .map(|(_, _, keys, _)| {
+                keys.iter()
+                    .map(|key| {
+                        KeyBinding::parse(key).expect("Invalid default key")
+                    })
+                    .collect()
+            })
+            .collect();
+        ModeKeys { actions, keys }
+    }
+
+    /// Return the message of the action bound to the `key`, if any.
+    pub fn get(&self, key: KeyEvent) -> Option<T> {
+        let binding = KeyBinding::from(key);
+        self.keys.iter()
+            .position(|keys| keys.contains(&binding))
+            .map(|i| self.actions[i].1.clone())
+    }
+
+    /// Return the keys bound to the action sending `msg`.
+    pub fn keys(&self, msg: &T) -> &[KeyBinding] {
+        match self.actions.iter().position(|(_, m, _, _)| m == msg) {
+            Some(i) => &self.keys[i],
+            None => &[],
+        }
+    }
+}
+
+// Bindings of a mode, independent of the type of its messages.
+trait ModeBindings {
+    // Bind the action `name` to the `keys` instead of its current keys.
+    fn rebind(&mut self, name: &str, keys: &[String]) -> Result<(), String>;
+
+    // Return a description of each key bound to more than one action.
+    fn conflicts(&self) -> Vec<String>;
+
+    // Return the name of the action bound to the `key`, if any.
+    fn bound_action(&self, key: &KeyBinding) -> Option<&'static str>;
+
+    // Return the help rows of the actions, in the order they are defined,
+    // leaving out the keys that type a character if `is_typing`.
+    fn help(&self, is_typing: bool) -> Vec<HelpRow>;
+}
+
+impl<T: Clone + PartialEq> ModeBindings for ModeKeys<T> {
+    fn rebind(&mut self, name: &str, keys: &[String]) -> Result<(), String> {
+        let i = self.actions.iter()
+            .position(|(action, _, _, _)| *action == name)
+            .ok_or_else(|| format!("unknown action \"{name}\""))?;
+        self.keys[i] = keys.iter()
+            .map(|key| {
+                KeyBinding::parse(key)
+                    .ok_or_else(|| format!("invalid key \"{key}\""))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+
+    fn conflicts(&self) -> Vec<String> {
+        let mut bound = HashMap::new();
+        let mut conflicts = Vec::new();
+        for ((name, _, _, _), keys) in self.actions.iter().zip(&self.keys) {
+            for key in keys {
+                match bound.insert(key, name) {
+                    Some(other) if other != name => {
+                        conflicts.push(format!(
+                            "\"{key}\" is bound to both \"{other}\" and \
+                             \"{name}\""
+                        ));
+                    }
+                    _ => (),
+                }
+            }
+        }
+        conflicts
+    }
+
+    fn bound_action(&self, key: &KeyBinding) -> Option<&'static str> {
+        self.keys.iter()
+            .position(|keys| keys.contains(key))
+            .map(|i| self.actions[i].0)
+    }
+
+    fn help(&self, is_typing: bool) -> Vec<HelpRow> {
+        self.actions.iter()
+            .zip(&self.keys)
+            .map(|((name, _, _, description), keys)| {
+                let labels: Vec<String> = keys.iter()
+                    .filter(|key| !(is_typing && key.is_typed()))
+                    .map(ToString::to_string)
+                    .collect();
+                HelpRow { keys: labels.join(" "), name, description }
+            })
+            .collect()
+    }
+}
+
+// Help rows of the characters typed in modes without a key binding for them.
+const TYPE_INDEX: (&str, &str) = ("0-9", "Type an index to jump to it");
+const TYPE_TEXT: (&str, &str) = ("Text", "Type text at the cursor");
+const TYPE_COUNT: (&str, &str) = ("0-9", "Type the count after its key");
+const TYPE_REGISTER: (&str, &str) =
+    ("a-z", "Type the register, after the number of times to play it");
+
+const GLOBAL: &[Action<HelpMsg>] = &[
+    (
+        "help",
+        HelpMsg::Open,
+        &["?", "f1"],
+        "Show the keys of the current mode",
+    ),
+];
+
+const HELP: &[Action<HelpMsg>] = &[
+    ("up", HelpMsg::Up, &["k", "up"], "Previous key binding"),
+    ("down", HelpMsg::Down, &["j", "down"], "Next key binding"),
+    ("close", HelpMsg::Close, &["esc", "q", "?", "f1"], "Close the help"),
+];
+
+const CONFIRM: &[Action<bool>] = &[
+    ("confirm", true, &["enter"], "Confirm"),
+    ("cancel", false, &["esc"], "Cancel"),
+];
+
+const LOAD: &[Action<LoadMsg>] = &[
+    ("up", LoadMsg::Decrement, &["k", "up"], "Previous file"),
+    ("down", LoadMsg::Increment, &["j", "down"], "Next file"),
+    ("open", LoadMsg::Open, &["enter"], "Open the selected file"),
+    ("new", LoadMsg::New, &["n"], "Start a new session"),
+    ("rename", LoadMsg::Rename, &["r"], "Rename the selected file"),
+    ("delete", LoadMsg::Delete, &["d"], "Delete the selected file"),
+    ("quit", LoadMsg::Quit, &["q"], "Quit"),
+];
+
+const NORMAL: &[Action<NormalMsg>] = &[
+    ("add", NormalMsg::Input, &["a"], "Add an item after the cursor"),
+    ("select", NormalMsg::Select, &["s"], "Select an item"),
+    ("paste", NormalMsg::Paste, &["p"], "Paste the register after the cursor"),
+    ("unhoist", NormalMsg::Unhoist, &["Z"], "Leave the hoisted item"),
+    ("command", NormalMsg::Command, &[":"], "Enter a command by name"),
+    (
+        "record",
+        NormalMsg::Record,
+        &["Q"],
+        "Record a macro, or stop recording",
+    ),
+    ("play", NormalMsg::Play, &["@"], "Play a macro"),
+    ("load", NormalMsg::Load, &["l"], "Open another file"),
+    ("quit", NormalMsg::Quit, &["q"], "Quit"),
+];
+
+const INPUT: &[Action<InputMsg>] = &[
+    ("submit", InputMsg::Submit, &["enter"], "Submit the input"),
+    ("cancel", InputMsg::Cancel, &["esc"], "Cancel"),
+    (
+        "complete",
+        InputMsg::Complete,
+        &["tab"],
+        "Complete the command name",
+    ),
+    (
+        "backspace",
+        InputMsg::Edit(InputEdit::Backspace),
+        &["backspace"],
+        "Delete the character before the cursor",
+    ),
+    (
+        "delete",
+        InputMsg::Edit(InputEdit::Delete),
+        &["delete"],
+        "Delete the character at the cursor",
+    ),
+    ("left", InputMsg::Edit(InputEdit::Left), &["left"], "Move left"),
+    ("right", InputMsg::Edit(InputEdit::Right), &["right"], "Move right"),
+    (
+        "word_left",
+        InputMsg::Edit(InputEdit::WordLeft),
+        &["ctrl+left", "alt+left"],
+        "Move to the previous word",
+    ),
+    (
+        "word_right",
+        InputMsg::Edit(InputEdit::WordRight),
+        &["ctrl+right", "alt+right"],
+        "Move to the next word",
+    ),
+    (
+        "home",
+        InputMsg::Edit(InputEdit::Home),
+        &["home", "ctrl+a"],
+        "Move to the start",
+    ),
+    (
+        "end",
+        InputMsg::Edit(InputEdit::End),
+        &["end", "ctrl+e"],
+        "Move to the end",
+    ),
+    (
+        "kill_word",
+        InputMsg::Edit(InputEdit::KillWord),
+        &["ctrl+w", "ctrl+backspace", "alt+backspace"],
+        "Delete the word before the cursor",
+    ),
+    (
+        "kill_to_start",
+        InputMsg::Edit(InputEdit::KillToStart),
+        &["ctrl+u"],
+        "Delete to the start",
+    ),
+    (
+        "kill_to_end",
+        InputMsg::Edit(InputEdit::KillToEnd),
+        &["ctrl+k"],
+        "Delete to the end",
+    ),
+];
+
+const SELECT: &[Action<SelectMsg>] = &[
+    ("up", SelectMsg::Decrement, &["k", "up"], "Previous item"),
+    ("down", SelectMsg::Increment, &["j", "down"], "Next item"),
+    (
+        "parent",
+        SelectMsg::Jump(Motion::Parent),
+        &["h", "left"],
+        "Jump to the parent",
+    ),
+    (
+        "first_child",
+        SelectMsg::Jump(Motion::FirstChild),
+        &["l", "right"],
+        "Jump to the first child",
+    ),
+    (
+        "next_sibling",
+        SelectMsg::Jump(Motion::NextSibling),
+        &["}"],
+        "Jump to the next sibling",
+    ),
+    (
+        "prev_sibling",
+        SelectMsg::Jump(Motion::PrevSibling),
+        &["{"],
+        "Jump to the previous sibling",
+    ),
+    (
+        "first_root",
+        SelectMsg::Jump(Motion::FirstRoot),
+        &["g"],
+        "Jump to the first root",
+    ),
+    (
+        "last_root",
+        SelectMsg::Jump(Motion::LastRoot),
+        &["G"],
+        "Jump to the last root",
+    ),
+    (
+        "next_at_depth",
+        SelectMsg::Jump(Motion::NextAtDepth),
+        &["n"],
+        "Jump to the next item at the same depth",
+    ),
+    (
+        "count",
+        SelectMsg::Count,
+        &["c"],
+        "Type a count of times to repeat the next motion",
+    ),
+    (
+        "repeat",
+        SelectMsg::Repeat,
+        &["."],
+        "Repeat the last command on the item",
+    ),
+    ("unhoist", SelectMsg::Unhoist, &["Z"], "Leave the hoisted item"),
+    ("mark", SelectMsg::ToggleMark, &["space"], "Mark or unmark the item"),
+    ("mark_down", SelectMsg::MarkDown, &["J"], "Mark down to the next item"),
+    ("mark_up", SelectMsg::MarkUp, &["K"], "Mark up to the previous item"),
+    ("unmark_all", SelectMsg::ClearMarks, &["u"], "Unmark all items"),
+    (
+        "cut_marked",
+        SelectMsg::Bulk(BulkMsg::Cut),
+        &["x"],
+        "Cut the marked items",
+    ),
+    (
+        "delete_marked",
+        SelectMsg::Bulk(BulkMsg::Delete),
+        &["D"],
+        "Delete the marked items",
+    ),
+    (
+        "move_marked",
+        SelectMsg::Bulk(BulkMsg::Move),
+        &["t"],
+        "Move the marked items to a target",
+    ),
+    (
+        "sort_marked",
+        SelectMsg::Bulk(BulkMsg::Sort),
+        &["o"],
+        "Sort the children of the marked items",
+    ),
+    (
+        "tag_marked",
+        SelectMsg::Bulk(BulkMsg::Tag),
+        &["#"],
+        "Tag the marked items",
+    ),
+    ("command", SelectMsg::Command, &[":"], "Enter a command by name"),
+    (
+        "confirm",
+        SelectMsg::Confirm,
+        &["enter"],
+        "Choose a command for the item",
+    ),
+    ("cancel", SelectMsg::Cancel, &["esc"], "Cancel"),
+];
+
+const SELECTED: &[Action<SelectedMsg>] = &[
+    ("edit", SelectedMsg::Edit, &["e"], "Edit the label"),
+    ("move", SelectedMsg::Move, &["m"], "Move the subtree step by step"),
+    ("delete", SelectedMsg::Delete, &["d"], "Delete the item"),
+    (
+        "delete_tree",
+        SelectedMsg::DeleteTree,
+        &["D"],
+        "Delete the item and its descendants",
+    ),
+    ("cut", SelectedMsg::Cut, &["x"], "Cut the subtree"),
+    ("copy", SelectedMsg::Copy, &["y"], "Copy the subtree"),
+    ("copy_path", SelectedMsg::CopyPath, &["Y"], "Copy the item's path"),
+    (
+        "paste_after",
+        SelectedMsg::Paste(Placement::After),
+        &["p"],
+        "Paste the register after the item",
+    ),
+    (
+        "paste_before",
+        SelectedMsg::Paste(Placement::Before),
+        &["P"],
+        "Paste the register before the item",
+    ),
+    (
+        "paste_child",
+        SelectedMsg::Paste(Placement::FirstChild),
+        &["c"],
+        "Paste the register as the first child",
+    ),
+    ("move_to", SelectedMsg::MoveTo, &["t"], "Move the subtree to a target"),
+    ("duplicate", SelectedMsg::Duplicate, &["u"], "Duplicate the subtree"),
+    (
+        "duplicate_rewrite",
+        SelectedMsg::DuplicateRewrite,
+        &["U"],
+        "Duplicate the subtree, replacing a prefix or suffix",
+    ),
+    ("wrap", SelectedMsg::Wrap, &["w"], "Wrap siblings in a new parent"),
+    ("hoist", SelectedMsg::Hoist, &["z"], "Show only the item's children"),
+    ("sort", SelectedMsg::Sort, &["o"], "Sort the children"),
+    ("command", SelectedMsg::Command, &[":"], "Enter a command by name"),
+    ("cancel", SelectedMsg::Cancel, &["esc"], "Cancel"),
+];
+
+const MOVE: &[Action<MoveMsg>] = &[
+    ("down", MoveMsg::Forward, &["j", "down"], "Swap with the next sibling"),
+    (
+        "up",
+        MoveMsg::Backward,
+        &["k", "up"],
+        "Swap with the previous sibling",
+    ),
+    (
+        "promote",
+        MoveMsg::Promote,
+        &["h", "left"],
+        "Move after the parent",
+    ),
+    (
+        "demote",
+        MoveMsg::Demote,
+        &["l", "right"],
+        "Move into the previous sibling",
+    ),
+    (
+        "count",
+        MoveMsg::Count,
+        &["c"],
+        "Type a count of times to repeat the next move",
+    ),
+    ("done", MoveMsg::Done, &["enter"], "Finish moving"),
+    ("cancel", MoveMsg::Cancel, &["esc"], "Cancel"),
+];
+
+const TARGET: &[Action<TargetMsg>] = &[
+    ("up", TargetMsg::Decrement, &["k", "up"], "Previous target"),
+    ("down", TargetMsg::Increment, &["j", "down"], "Next target"),
+    (
+        "first_child",
+        TargetMsg::Place(Placement::FirstChild),
+        &["f"],
+        "Move to the target's first child",
+    ),
+    (
+        "last_child",
+        TargetMsg::Place(Placement::LastChild),
+        &["l"],
+        "Move to the target's last child",
+    ),
+    (
+        "before",
+        TargetMsg::Place(Placement::Before),
+        &["b"],
+        "Move before the target",
+    ),
+    (
+        "after",
+        TargetMsg::Place(Placement::After),
+        &["a"],
+        "Move after the target",
+    ),
+    ("cancel", TargetMsg::Cancel, &["esc"], "Cancel"),
+];
+
+const WRAP: &[Action<WrapMsg>] = &[
+    ("more", WrapMsg::Extend, &["j", "down"], "Include the next sibling"),
+    ("fewer", WrapMsg::Shrink, &["k", "up"], "Exclude the last sibling"),
+    ("confirm", WrapMsg::Confirm, &["enter"], "Enter the new parent's label"),
+    ("cancel", WrapMsg::Cancel, &["esc"], "Cancel"),
+];
+
+const MACRO: &[Action<MacroMsg>] = &[
+    ("cancel", MacroMsg::Cancel, &["esc"], "Cancel"),
+];
+
+const SAVE: &[Action<SaveMsg>] = &[
+    ("toggle", SaveMsg::Toggle, &["space"], "Toggle saving or discarding"),
+    ("confirm", SaveMsg::Confirm, &["enter"], "Confirm"),
+    ("cancel", SaveMsg::Cancel, &["esc"], "Cancel"),
+];
+
+// Return the message of the action called `name` among the `actions`.
+fn find_action<T: Clone>(actions: &[Action<T>], name: &str) -> Option<T> {
+    actions.iter()
+        .find(|(action, _, _, _)| *action == name)
+        .map(|(_, msg, _, _)| msg.clone())
+}
+
+/// An action that can be run by name from the command line.
+pub enum NamedAction {
+    Normal(NormalMsg),
+    Select(SelectMsg),
+    Selected(SelectedMsg),
+}
+
+/// Return the action called `name` in Normal, Selected or Select mode, in
+/// that order of precedence.
+pub fn named_action(name: &str) -> Option<NamedAction> {
+    find_action(NORMAL, name)
+        .map(NamedAction::Normal)
+        .or_else(|| find_action(SELECTED, name).map(NamedAction::Selected))
+        .or_else(|| find_action(SELECT, name).map(NamedAction::Select))
+}
+
+/// Return the names of the actions that can be run from the command line.
+pub fn action_names() -> impl Iterator<Item = &'static str> {
+    NORMAL.iter()
+        .map(|(name, _, _, _)| *name)
+        .chain(SELECTED.iter().map(|(name, _, _, _)| *name))
+        .chain(SELECT.iter().map(|(name, _, _, _)| *name))
+}
+
+/// Return the name of the `mode` in the config file.
+pub fn mode_name(mode: &Mode) -> &'static str {
+    match mode {
+        Mode::Confirm(_) => "confirm",
+        Mode::Load(_) => "load",
+        Mode::Normal => "normal",
+        Mode::Input(_) => "input",
+        Mode::Select(_) => "select",
+        Mode::Selected(_) => "selected",
+        Mode::Move(_) => "move",
+        Mode::Target(_) => "target",
+        Mode::Wrap(_) => "wrap",
+        Mode::Macro(_) => "macro",
+        Mode::Save(_) => "save",
+        Mode::Help(_) => "help",
+    }
+}
+
+/// The keys bound to the actions of each mode, and to the global actions
+/// available in every mode.
+pub struct Keymap {
+    pub global: ModeKeys<HelpMsg>,
+    pub help: ModeKeys<HelpMsg>,
+    pub confirm: ModeKeys<bool>,
+    pub load: ModeKeys<LoadMsg>,
+    pub normal: ModeKeys<NormalMsg>,
+    pub input: ModeKeys<InputMsg>,
+    pub select: ModeKeys<SelectMsg>,
+    pub selected: ModeKeys<SelectedMsg>,
+    pub move_: ModeKeys<MoveMsg>,
+    pub target: ModeKeys<TargetMsg>,
+    pub wrap: ModeKeys<WrapMsg>,
+    pub macro_: ModeKeys<MacroMsg>,
+    pub save: ModeKeys<SaveMsg>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            global: ModeKeys::new(GLOBAL),
+            help: ModeKeys::new(HELP),
+            confirm: ModeKeys::new(CONFIRM),
+            load: ModeKeys::new(LOAD),
+            normal: ModeKeys::new(NORMAL),
+            input: ModeKeys::new(INPUT),
+            select: ModeKeys::new(SELECT),
+            selected: ModeKeys::new(SELECTED),
+            move_: ModeKeys::new(MOVE),
+            target: ModeKeys::new(TARGET),
+            wrap: ModeKeys::new(WRAP),
+            macro_: ModeKeys::new(MACRO),
+            save: ModeKeys::new(SAVE),
+        }
+    }
+}
+
+impl Keymap {
+    // Return the bindings of each mode with the mode name.
+    fn modes(&self) -> [(&'static str, &dyn ModeBindings); 13] {
+        [
+            ("global", &self.global),
+            ("help", &self.help),
+            ("confirm", &self.confirm),
+            ("load", &self.load),
+            ("normal", &self.normal),
+            ("input", &self.input),
+            ("select", &self.select),
+            ("selected", &self.selected),
+            ("move", &self.move_),
+            ("target", &self.target),
+            ("wrap", &self.wrap),
+            ("macro", &self.macro_),
+            ("save", &self.save),
+        ]
+    }
+
+    // Return the bindings of each mode with the mode name, for rebinding.
+    fn modes_mut(&mut self) -> [(&'static str, &mut dyn ModeBindings); 13] {
+        [
+            ("global", &mut self.global),
+            ("help", &mut self.help),
+            ("confirm", &mut self.confirm),
+            ("load", &mut self.load),
+            ("normal", &mut self.normal),
+            ("input", &mut self.input),
+            ("select", &mut self.select),
+            ("selected", &mut self.selected),
+            ("move", &mut self.move_),
+            ("target", &mut self.target),
+            ("wrap", &mut self.wrap),
+            ("macro", &mut self.macro_),
+            ("save", &mut self.save),
+        ]
+    }
+
+    // Return a description of each global key also bound in a mode where the
+    // global action takes precedence.
+    //
+    // Help mode handles its own keys, and Input mode inserts typed
+    // characters instead.
+    fn global_conflicts(&self) -> Vec<String> {
+        let global: Vec<(&str, KeyBinding)> = GLOBAL.iter()
+            .zip(&self.global.keys)
+            .flat_map(|((name, _, _, _), keys)| {
+                keys.iter().map(|key| (*name, *key))
+            })
+            .collect();
+        let mut conflicts = Vec::new();
+        for (mode, mode_keys) in self.modes() {
+            for (global_name, key) in &global {
+                let is_shadowed = match mode {
+                    "global" | "help" => false,
+                    "input" => !key.is_typed(),
+                    _ => true,
+                };
+                match mode_keys.bound_action(key) {
+                    Some(name) if is_shadowed => conflicts.push(format!(
+                        "keys.{mode}: \"{key}\" is bound to both \"{name}\" \
+                         and global \"{global_name}\""
+                    )),
+                    _ => (),
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Return the help rows of the actions available in the `mode`, followed
+    /// by the global actions.
+    pub fn help(&self, mode: &Mode) -> Vec<HelpRow> {
+        let (mode_keys, typed): (&dyn ModeBindings, _) = match mode {
+            Mode::Confirm(_) => (&self.confirm, None),
+            Mode::Load(_) => (&self.load, Some(TYPE_INDEX)),
+            Mode::Normal => (&self.normal, None),
+            Mode::Input(_) => (&self.input, Some(TYPE_TEXT)),
+            Mode::Select(_) => (&self.select, Some(TYPE_INDEX)),
+            Mode::Selected(_) => (&self.selected, None),
+            Mode::Move(_) => (&self.move_, Some(TYPE_COUNT)),
+            Mode::Target(_) => (&self.target, Some(TYPE_INDEX)),
+            Mode::Wrap(_) => (&self.wrap, None),
+            Mode::Macro(_) => (&self.macro_, Some(TYPE_REGISTER)),
+            Mode::Save(_) => (&self.save, None),
+            Mode::Help(_) => return self.help.help(false),
+        };
+        let mut rows = mode_keys.help(false);
+        if let Some((keys, description)) = typed {
+            let keys = keys.to_string();
+            rows.push(HelpRow { keys, name: "", description });
+        }
+        let is_typing = matches!(mode, Mode::Input(_));
+        rows.extend(self.global.help(is_typing));
+        rows
+    }
+
+    /// Create a Keymap binding the actions named in `bindings` (by mode, then
+    /// action) and keeping the default keys of the others.
+    ///
+    /// Returns a description of each invalid binding or conflicting key on
+    /// failure.
+    pub fn new(
+        bindings: &BTreeMap<String, BTreeMap<String, Keys>>,
+    ) -> Result<Self, Vec<String>> {
+        let mut keymap = Keymap::default();
+        let mut errors = Vec::new();
+        for mode in bindings.keys() {
+            if !keymap.modes().iter().any(|(name, _)| name == mode) {
+                errors.push(format!("keys.{mode}: unknown mode"));
+            }
+        }
+        for (mode, mode_keys) in keymap.modes_mut() {
+            for (name, keys) in bindings.get(mode).into_iter().flatten() {
+                if let Err(error) = mode_keys.rebind(name, keys.as_slice()) {
+                    errors.push(format!("keys.{mode}: {error}"));
+                }
+            }
+            for conflict in mode_keys.conflicts() {
+                errors.push(format!("keys.{mode}: {conflict}"));
+            }
+        }
+        errors.extend(keymap.global_conflicts());
+        match errors.is_empty() {
+            true => Ok(keymap),
+            false => Err(errors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::InputState;
+
+    use super::*;
+
+    #[test]
+    fn parse_and_show_keys() {
+        let show = |key| KeyBinding::parse(key).unwrap().to_string();
+
+        assert_eq!(show("j"), "J");
+        assert_eq!(show("J"), "⇧J");
+        assert_eq!(show("space"), "Space");
+        assert_eq!(show("Ctrl+w"), "Ctrl+W");
+        assert_eq!(show("alt+left"), "Alt+←");
+        assert_eq!(show("+"), "+");
+        assert_eq!(show("shift+j"), "⇧J");
+        assert_eq!(KeyBinding::parse("shift+j"), KeyBinding::parse("J"));
+        assert_eq!(show("shift+tab"), "⇧Tab");
+        assert_eq!(KeyBinding::parse("ctrl+"), None);
+        assert_eq!(KeyBinding::parse("enter2"), None);
+        assert_eq!(KeyBinding::parse("shift+1"), None);
+        for key in [
+            "J", "space", "ctrl+w", "alt+left", "+", "f1", "shift+tab",
+        ] {
+            let name = KeyBinding::parse(key).and_then(|key| key.name());
+            assert_eq!(name.as_deref(), Some(key));
+        }
+    }
+
+    #[test]
+    fn rebind_and_report_conflicts() {
+        let parse = |text| toml::from_str(text).unwrap();
+        let keymap = Keymap::new(&parse("normal.add = ['i', 'o']")).unwrap();
+        let key = KeyEvent::new(KeyCode::Char('o'), KeyModifiers::NONE);
+
+        assert!(keymap.normal.get(key) == Some(NormalMsg::Input));
+        assert_eq!(keymap.normal.keys(&NormalMsg::Input).len(), 2);
+        let errors = Keymap::new(&parse("normal.add = 's'\nfoo.bar = 'x'"))
+            .err()
+            .unwrap();
+        assert_eq!(errors, vec![
+            "keys.foo: unknown mode",
+            "keys.normal: \"S\" is bound to both \"add\" and \"select\"",
+        ]);
+    }
+
+    #[test]
+    fn list_help_and_report_global_conflicts() {
+        let parse = |text| toml::from_str(text).unwrap();
+        let mut keymap = Keymap::default();
+        let modes = keymap.modes().map(|(mode, _)| mode);
+        assert_eq!(modes, keymap.modes_mut().map(|(mode, _)| mode));
+        let normal = keymap.help(&Mode::Normal);
+        let input = keymap.help(&Mode::Input(InputState::new_add()));
+
+        assert_eq!(normal.len(), NORMAL.len() + GLOBAL.len());
+        assert_eq!((normal[0].keys.as_str(), normal[0].name), ("A", "add"));
+        assert_eq!(normal[normal.len() - 1].keys, "? F1");
+        assert_eq!(input[input.len() - 2].keys, TYPE_TEXT.0);
+        assert_eq!(input[input.len() - 1].keys, "F1");
+        assert!(Keymap::new(&parse("input.submit = ['enter', '?']")).is_ok());
+        assert_eq!(
+            Keymap::new(&parse("normal.add = '?'")).err().unwrap(),
+            vec!["keys.normal: \"?\" is bound to both \"add\" and global \
+                \"help\""],
+        );
+    }
+}
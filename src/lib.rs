@@ -0,0 +1,32 @@
+//! Outline forests of labeled nodes, and the data files storing them.
+//!
+//! - [`forest`]: the [`Node`](forest::Node) forest, edited by pre-order index
+//!   or with a [`Cursor`](forest::Cursor), and iterated with
+//!   [`Node::iter`](forest::Node::iter)
+//! - [`format`](mod@format): loading and saving data files, and exporting to
+//!   text
+//! - [`storage`]: named data files on the filesystem or in memory
+//!
+//! ```
+//! use elmlog::{
+//!     forest::{IdGen, Item, Node, Placement},
+//!     format,
+//!     storage::MemoryStorage,
+//! };
+//!
+//! let mut ids = IdGen::default();
+//! let root = Node::Empty.prepend(Item::new(ids.next_id(), "Inbox".into()));
+//! let task = Node::Empty.prepend(Item::new(ids.next_id(), "Call".into()));
+//! let (root, cursor) = root.insert(0, task, Placement::FirstChild);
+//!
+//! let storage = MemoryStorage::new();
+//! format::save(&storage, "notes", &root, &ids, cursor)?;
+//! let (root, _, cursor) = format::load(&storage, "notes")?;
+//! assert_eq!(root.outline(), vec![(0, "Inbox"), (1, "Call")]);
+//! assert_eq!(root.find_label(cursor), "Call");
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+pub mod forest;
+pub mod format;
+pub mod storage;
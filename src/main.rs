@@ -1,42 +1,130 @@
-mod forest;
+mod cmdline;
+mod config;
+mod editor;
 mod io;
+mod keymap;
 mod message;
 mod model;
+mod script;
+mod theme;
 mod update;
 mod util;
 mod view;
 
-use std::io::Result;
+use std::{
+    env,
+    fs,
+    io::{stdin, stdout, Read, Result},
+    path::PathBuf,
+    process,
+};
 
+use crossterm::{
+    event::{
+        self,
+        DisableBracketedPaste,
+        DisableMouseCapture,
+        EnableBracketedPaste,
+        EnableMouseCapture,
+    },
+    execute,
+};
+use elmlog::{forest, format, storage};
 use ratatui::DefaultTerminal;
 
 use crate::{
+    config::Config,
+    forest::Node,
     io::execute_command,
     message::{Command, handle_event},
     model::Model,
     update::update,
+    storage::FsStorage,
     view::view,
 };
 
-fn main_loop(mut terminal: DefaultTerminal) -> Result<()> {
-    let mut model = execute_command(Command::Load).unwrap();
+// Usage of the command line arguments.
+const USAGE: &str = "Usage: elmlog [--script <file>|- --dir <dir> \
+    [--size <columns>x<rows>]]";
+
+fn main_loop(
+    mut terminal: DefaultTerminal,
+    config: Config,
+    storage: FsStorage,
+) -> Result<()> {
+    let mut model = execute_command(Command::Load(Node::Empty), &storage)
+        .unwrap();
     loop {
-        terminal.draw(|frame| view(&model, frame))?;
+        let area = terminal.draw(|frame| view(&model, &config, frame))?.area;
         let Model { state, mode } = model;
-        let message = handle_event(mode)?;
+        let read_event = || event::read().map(Some);
+        let Some(message) =
+            handle_event(mode, &state, &config, area, read_event)?
+        else {
+            return Ok(());
+        };
         let command = update(message, state);
-        model = match execute_command(command) {
+        model = match execute_command(command, &storage) {
             Some(updated_model) => updated_model,
             None => return Ok(()),
         }
     }
 }
 
+// Run the key script given in the command line `args` on a headless terminal,
+// with its output in the given directory.
+//
+// Returns a description of the problem on failure.
+fn run_script(
+    mut args: impl Iterator<Item = String>,
+) -> std::result::Result<(), String> {
+    let (mut script, mut dir, mut size) = (None, None, script::DEFAULT_SIZE);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next().ok_or_else(|| format!("Missing value for {arg}"))
+        };
+        match arg.as_str() {
+            "--script" => script = Some(value()?),
+            "--dir" => dir = Some(PathBuf::from(value()?)),
+            "--size" => size = script::parse_size(&value()?)?,
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let (Some(script), Some(dir)) = (script, dir) else {
+        return Err(USAGE.to_string());
+    };
+    let text = match script.as_str() {
+        "-" => {
+            let mut text = String::new();
+            stdin().read_to_string(&mut text).map(|_| text)
+        }
+        path => fs::read_to_string(path),
+    };
+    let text = text.map_err(|error| format!("{script}: {error}"))?;
+    let steps = script::parse(&text)
+        .map_err(|error| format!("Invalid script {script}: {error}"))?;
+    script::run(steps, &dir, size)
+}
+
 fn main() -> Result<()> {
+    let mut args = env::args().skip(1).peekable();
+    if args.peek().is_some() {
+        if let Err(error) = run_script(args) {
+            eprintln!("{error}");
+            process::exit(1);
+        }
+        return Ok(());
+    }
+    let storage = FsStorage::user();
+    let config = Config::load(&storage).unwrap_or_else(|error| {
+        eprintln!("{error}");
+        process::exit(1);
+    });
     let mut terminal = ratatui::init();
+    execute!(stdout(), EnableBracketedPaste, EnableMouseCapture)?;
     terminal.clear()?;
-    let result = main_loop(terminal);
+    let result = main_loop(terminal, config, storage);
+    execute!(stdout(), DisableBracketedPaste, DisableMouseCapture)?;
     ratatui::restore();
     result
 }
-
@@ -1,24 +1,48 @@
 use std::{
     io::Result,
+    path::PathBuf,
+    time::Instant,
 };
 
-use crossterm::event::{self, KeyCode, KeyEventKind};
+use crossterm::event::{
+    Event,
+    KeyCode,
+    KeyEvent,
+    KeyEventKind,
+    KeyModifiers,
+    MouseButton,
+    MouseEvent,
+    MouseEventKind,
+};
+use ratatui::layout::Rect;
 
 use crate::{
-    io::{FileEntry, LoadState},
+    config::Config,
+    forest::{Motion, Node, Placement},
+    format::ExportFormat,
+    io::LoadState,
+    keymap::{KeyBinding, Keymap, ModeKeys},
     model::{
+        CommandLineState,
         ConfirmState,
         FilenameState,
+        HelpState,
         InputState,
+        MacroState,
         Mode,
         Model,
+        MousePress,
         PostSaveAction,
         SaveState,
         SessionState,
+        TargetState,
+        WrapState,
     },
+    view,
 };
 
 /// A message sent in Load mode.
+#[derive(Clone, PartialEq)]
 pub enum LoadMsg {
     Append(char),
     Decrement,
@@ -31,57 +55,166 @@ pub enum LoadMsg {
 }
 
 /// A message sent in Normal mode.
+#[derive(Clone, PartialEq)]
 pub enum NormalMsg {
     Input,
     Select,
+    Paste,
+    Unhoist,
+    Command,
+    Record,
+    Play,
     Load,
     Quit,
 }
 
 /// Type of edit to apply to the user input text.
+#[derive(Clone, PartialEq)]
 pub enum InputEdit {
-    Append(char),
-    PopChar,
+    Insert(char),
+    Paste(String),
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Home,
+    End,
+    KillWord,
+    KillToStart,
+    KillToEnd,
 }
 
 /// A message sent in Input mode.
+#[derive(Clone, PartialEq)]
 pub enum InputMsg {
     Edit(InputEdit),
     Submit,
     Cancel,
+    Complete,
+}
+
+/// An operation applied to all marked items in Select mode.
+#[derive(Clone, PartialEq)]
+pub enum BulkMsg {
+    Cut,
+    Delete,
+    Move,
+    Sort,
+    Tag,
 }
 
 /// A message sent in Select mode.
+#[derive(Clone, PartialEq)]
 pub enum SelectMsg {
     Append(char),
     Decrement,
     Increment,
+    Jump(Motion),
+    Unhoist,
+    ToggleMark,
+    MarkDown,
+    MarkUp,
+    ClearMarks,
+    Bulk(BulkMsg),
+    Count,
+    Repeat,
+    Command,
     Confirm,
+    Cancel,
 }
 
 /// A message sent in Selected mode.
+#[derive(Clone, PartialEq)]
 pub enum SelectedMsg {
     Edit,
     Move,
     Delete,
+    DeleteTree,
+    Cut,
+    Copy,
+    CopyPath,
+    Paste(Placement),
+    MoveTo,
+    Duplicate,
+    DuplicateRewrite,
+    Wrap,
+    Hoist,
+    Sort,
+    Command,
+    Cancel,
+}
+
+/// A message sent in Target mode.
+#[derive(Clone, PartialEq)]
+pub enum TargetMsg {
+    Append(char),
+    Decrement,
+    Increment,
+    Place(Placement),
+    Cancel,
 }
 
 /// A message sent in Move mode.
+#[derive(Clone, PartialEq)]
 pub enum MoveMsg {
+    Append(char),
+    Count,
     Forward,
     Backward,
     Promote,
     Demote,
     Done,
+    Cancel,
+}
+
+/// A message sent in Wrap mode.
+#[derive(Clone, PartialEq)]
+pub enum WrapMsg {
+    Extend,
+    Shrink,
+    Confirm,
+    Cancel,
+}
+
+/// A message sent in Macro mode.
+#[derive(Clone, PartialEq)]
+pub enum MacroMsg {
+    Append(char),
+    Cancel,
 }
 
 /// A message sent in Save mode.
+#[derive(Clone, PartialEq)]
 pub enum SaveMsg {
     Toggle,
     Confirm,
+    Cancel,
+}
+
+/// A message sent to open the help, or in Help mode.
+#[derive(Clone, PartialEq)]
+pub enum HelpMsg {
+    Open,
+    Up,
+    Down,
+    Close,
+}
+
+/// A mouse action, with the item under the pointer when pressed or released.
+pub enum MouseMsg {
+    Press(MousePress),
+    Drag,
+    Release(usize),
+    ScrollUp,
+    ScrollDown,
 }
 
 /// A message indicating changes to be made to the model.
+///
+/// `Unrecordable` describes an input that cannot be recorded in a macro,
+/// which stops the recording instead.
 pub enum Message {
     Confirm(bool, ConfirmState),
     Load(LoadMsg, LoadState),
@@ -90,157 +223,407 @@ pub enum Message {
     Select(SelectMsg, usize),
     Selected(SelectedMsg, usize),
     Move(MoveMsg, usize),
+    Target(TargetMsg, TargetState),
+    Wrap(WrapMsg, WrapState),
+    Macro(MacroMsg, MacroState),
     Save(SaveMsg, SaveState),
+    Help(HelpMsg, HelpState),
+    PasteLines(String),
+    Mouse(MouseMsg, Mode),
+    Record(KeyBinding, Box<Message>),
+    Replay(Box<Message>),
+    Unrecordable(String),
     Continue(Mode),
 }
 
 /// A message indicating an IO action to perform.
+///
+/// The `Node` carried by load-related commands is the register to keep.
+/// Commands run from the command line carry it to show any error.
+/// Macro commands carry the register name.
 pub enum Command {
     None(Model),
-    Load,
-    InitSession(FileEntry),
+    Load(Node),
+    InitSession(String, Node),
     CheckFileExists(SessionState, FilenameState),
     Rename(SessionState, String, LoadState),
     SaveNew(SessionState, String, PostSaveAction),
     Save(SessionState, PostSaveAction),
-    DeleteFile(LoadState),
+    RenameOpen(SessionState, CommandLineState, String),
+    Export(SessionState, CommandLineState, ExportFormat, PathBuf),
+    DeleteFile(LoadState, Node),
+    Clipboard(Model, String),
+    SaveMacro(Model, char, Vec<KeyBinding>),
+    PlayMacro(Model, char, usize),
     Quit,
 }
 
 // Map a `key` to a Message in Confirm mode.
-fn to_confirm_msg(key: KeyCode, confirm_state: ConfirmState) -> Message {
-    let confirm = match key {
-        KeyCode::Enter => true,
-        KeyCode::Esc => false,
-        _ => return Message::Continue(Mode::Confirm(confirm_state)),
-    };
-    Message::Confirm(confirm, confirm_state)
-}
-
-// Map a `key` to a Message in Load mode.
-fn to_load_msg(key: KeyCode, load_state: LoadState) -> Message {
-    let load_msg = match key {
-        KeyCode::Char(c) => match c {
-            'k' => LoadMsg::Decrement,
-            'j' => LoadMsg::Increment,
-            'n' => LoadMsg::New,
-            'r' => LoadMsg::Rename,
-            'd' => LoadMsg::Delete,
-            'q' => LoadMsg::Quit,
-            _ => LoadMsg::Append(c),
-        }
-        KeyCode::Up => LoadMsg::Decrement,
-        KeyCode::Down => LoadMsg::Increment,
-        KeyCode::Enter => LoadMsg::Open,
-        _ => return Message::Continue(Mode::Load(load_state)),
+fn to_confirm_msg(
+    key: KeyEvent,
+    confirm_state: ConfirmState,
+    keys: &ModeKeys<bool>,
+) -> Message {
+    match keys.get(key) {
+        Some(confirm) => Message::Confirm(confirm, confirm_state),
+        None => Message::Continue(Mode::Confirm(confirm_state)),
+    }
+}
+
+// Map a `key` to a Message in Load mode, appending unbound characters to the
+// file index.
+fn to_load_msg(
+    key: KeyEvent,
+    load_state: LoadState,
+    keys: &ModeKeys<LoadMsg>,
+) -> Message {
+    let load_msg = match (keys.get(key), key.code) {
+        (Some(msg), _) => msg,
+        (None, KeyCode::Char(c)) => LoadMsg::Append(c),
+        (None, _) => return Message::Continue(Mode::Load(load_state)),
     };
     Message::Load(load_msg, load_state)
 }
 
 // Map a `key` to a Message in Normal mode.
-fn to_normal_msg(key: KeyCode) -> Message {
-    let normal_msg = match key {
-        KeyCode::Char('a') => NormalMsg::Input,
-        KeyCode::Char('s') => NormalMsg::Select,
-        KeyCode::Char('l') => NormalMsg::Load,
-        KeyCode::Char('q') => NormalMsg::Quit,
-        _ => return Message::Continue(Mode::Normal),
-    };
-    Message::Normal(normal_msg)
+fn to_normal_msg(key: KeyEvent, keys: &ModeKeys<NormalMsg>) -> Message {
+    match keys.get(key) {
+        Some(msg) => Message::Normal(msg),
+        None => Message::Continue(Mode::Normal),
+    }
 }
 
-// Map a `key` to a Message in Input mode.
-fn to_input_msg(key: KeyCode, input_state: InputState) -> Message {
-    let input_msg = match key {
-        KeyCode::Char(c) => InputMsg::Edit(InputEdit::Append(c)),
-        KeyCode::Backspace => InputMsg::Edit(InputEdit::PopChar),
-        KeyCode::Enter => InputMsg::Submit,
-        KeyCode::Esc => InputMsg::Cancel,
+// Map a `key` to a Message in Input mode, inserting unbound characters typed
+// without Ctrl.
+fn to_input_msg(
+    key: KeyEvent,
+    input_state: InputState,
+    keys: &ModeKeys<InputMsg>,
+) -> Message {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let input_msg = match (keys.get(key), key.code) {
+        (Some(msg), _) => msg,
+        (None, KeyCode::Char(c)) if !ctrl => {
+            InputMsg::Edit(InputEdit::Insert(c))
+        }
         _ => return Message::Continue(Mode::Input(input_state)),
     };
     Message::Input(input_msg, input_state)
 }
 
-// Return to Normal mode on Esc, otherwise continue in the given `mode`.
-fn default(key: KeyCode, mode: Mode) -> Message {
-    Message::Continue(match key {
-        KeyCode::Esc => Mode::Normal,
-        _ => mode,
-    })
-}
-
-// Map a `key` to a Message in Select mode.
-fn to_select_msg(key: KeyCode, index: usize) -> Message {
-    let select_msg = match key {
-        KeyCode::Char(c) => match c {
-            'k' => SelectMsg::Decrement,
-            'j' => SelectMsg::Increment,
-            _ => SelectMsg::Append(c),
-        }
-        KeyCode::Up => SelectMsg::Decrement,
-        KeyCode::Down => SelectMsg::Increment,
-        KeyCode::Enter => SelectMsg::Confirm,
-        _ => return default(key, Mode::Select(index)),
+// Map a `key` to a Message in Select mode, appending unbound characters to the
+// index.
+fn to_select_msg(
+    key: KeyEvent,
+    index: usize,
+    keys: &ModeKeys<SelectMsg>,
+) -> Message {
+    let select_msg = match (keys.get(key), key.code) {
+        (Some(msg), _) => msg,
+        (None, KeyCode::Char(c)) => SelectMsg::Append(c),
+        (None, _) => return Message::Continue(Mode::Select(index)),
     };
     Message::Select(select_msg, index)
 }
 
 // Map a `key` to a Message in Selected mode.
-fn to_selected_msg(key: KeyCode, index: usize) -> Message {
-    let selected_msg = match key {
-        KeyCode::Char('e') => SelectedMsg::Edit,
-        KeyCode::Char('m') => SelectedMsg::Move,
-        KeyCode::Char('d') => SelectedMsg::Delete,
-        _ => return default(key, Mode::Selected(index)),
-    };
-    Message::Selected(selected_msg, index)
-}
-
-// Map a `key` to a Message in Move mode.
-fn to_move_msg(key: KeyCode, index: usize) -> Message {
-    let move_msg = match key {
-        KeyCode::Char('j') | KeyCode::Down => MoveMsg::Forward,
-        KeyCode::Char('k')| KeyCode::Up => MoveMsg::Backward,
-        KeyCode::Char('h')| KeyCode::Left => MoveMsg::Promote,
-        KeyCode::Char('l')| KeyCode::Right => MoveMsg::Demote,
-        KeyCode::Enter => MoveMsg::Done,
-        _ => return default(key, Mode::Move(index)),
+fn to_selected_msg(
+    key: KeyEvent,
+    index: usize,
+    keys: &ModeKeys<SelectedMsg>,
+) -> Message {
+    match keys.get(key) {
+        Some(msg) => Message::Selected(msg, index),
+        None => Message::Continue(Mode::Selected(index)),
+    }
+}
+
+// Map a `key` to a Message in Move mode, appending unbound characters to the
+// count.
+fn to_move_msg(
+    key: KeyEvent,
+    index: usize,
+    keys: &ModeKeys<MoveMsg>,
+) -> Message {
+    let move_msg = match (keys.get(key), key.code) {
+        (Some(msg), _) => msg,
+        (None, KeyCode::Char(c)) => MoveMsg::Append(c),
+        (None, _) => return Message::Continue(Mode::Move(index)),
     };
     Message::Move(move_msg, index)
 }
 
-// Map a `key` to a Message in Save mode.
-fn to_save_msg(key: KeyCode, save_state: SaveState) -> Message {
-    let save_msg = match key {
-        KeyCode::Char(' ') => SaveMsg::Toggle,
-        KeyCode::Enter => SaveMsg::Confirm,
-        _ => return default(key, Mode::Save(save_state)),
+// Map a `key` to a Message in Target mode, appending unbound characters to the
+// target index.
+fn to_target_msg(
+    key: KeyEvent,
+    target_state: TargetState,
+    keys: &ModeKeys<TargetMsg>,
+) -> Message {
+    let target_msg = match (keys.get(key), key.code) {
+        (Some(msg), _) => msg,
+        (None, KeyCode::Char(c)) => TargetMsg::Append(c),
+        (None, _) => return Message::Continue(Mode::Target(target_state)),
+    };
+    Message::Target(target_msg, target_state)
+}
+
+// Map a `key` to a Message in Wrap mode.
+fn to_wrap_msg(
+    key: KeyEvent,
+    wrap_state: WrapState,
+    keys: &ModeKeys<WrapMsg>,
+) -> Message {
+    match keys.get(key) {
+        Some(msg) => Message::Wrap(msg, wrap_state),
+        None => Message::Continue(Mode::Wrap(wrap_state)),
+    }
+}
+
+// Map a `key` to a Message in Macro mode, appending unbound characters to the
+// count or register.
+fn to_macro_msg(
+    key: KeyEvent,
+    macro_state: MacroState,
+    keys: &ModeKeys<MacroMsg>,
+) -> Message {
+    let macro_msg = match (keys.get(key), key.code) {
+        (Some(msg), _) => msg,
+        (None, KeyCode::Char(c)) => MacroMsg::Append(c),
+        (None, _) => return Message::Continue(Mode::Macro(macro_state)),
     };
-    Message::Save(save_msg, save_state)
+    Message::Macro(macro_msg, macro_state)
+}
+
+// Map a `key` to a Message in Save mode.
+fn to_save_msg(
+    key: KeyEvent,
+    save_state: SaveState,
+    keys: &ModeKeys<SaveMsg>,
+) -> Message {
+    match keys.get(key) {
+        Some(msg) => Message::Save(msg, save_state),
+        None => Message::Continue(Mode::Save(save_state)),
+    }
+}
+
+// Map a `key` to a Message in Help mode.
+fn to_help_msg(
+    key: KeyEvent,
+    help_state: HelpState,
+    keys: &ModeKeys<HelpMsg>,
+) -> Message {
+    match keys.get(key) {
+        Some(msg) => Message::Help(msg, help_state),
+        None => Message::Continue(Mode::Help(help_state)),
+    }
+}
+
+// Return whether the `key` opens the help in the `mode`.
+//
+// Help mode handles its own keys, and Input mode inserts typed characters.
+fn opens_help(key: KeyEvent, mode: &Mode, keys: &ModeKeys<HelpMsg>) -> bool {
+    match mode {
+        Mode::Help(_) => false,
+        Mode::Input(_) if KeyBinding::from(key).is_typed() => false,
+        _ => keys.get(key) == Some(HelpMsg::Open),
+    }
 }
 
-// Map a pressed `key` to a Message based on the current `mode`.
-fn key_to_message(mode: Mode, key: KeyCode) -> Message {
+// Map a pressed `key` to a Message based on the current `mode` and the
+// `keymap`, giving precedence to the global actions.
+fn key_to_message(mode: Mode, key: KeyEvent, keymap: &Keymap) -> Message {
+    if opens_help(key, &mode, &keymap.global) {
+        let rows = keymap.help(&mode).len();
+        return Message::Help(HelpMsg::Open, HelpState::new(mode, rows));
+    }
     match mode {
-        Mode::Confirm(confirm_state) => to_confirm_msg(key, confirm_state),
-        Mode::Load(load_state) => to_load_msg(key, load_state),
-        Mode::Normal => to_normal_msg(key),
-        Mode::Input(input) => to_input_msg(key, input),
-        Mode::Select(index) => to_select_msg(key, index),
-        Mode::Selected(index) => to_selected_msg(key, index),
-        Mode::Move(index) => to_move_msg(key, index),
-        Mode::Save(save_state) => to_save_msg(key, save_state),
+        Mode::Confirm(confirm_state) => {
+            to_confirm_msg(key, confirm_state, &keymap.confirm)
+        }
+        Mode::Load(load_state) => to_load_msg(key, load_state, &keymap.load),
+        Mode::Normal => to_normal_msg(key, &keymap.normal),
+        Mode::Input(input) => to_input_msg(key, input, &keymap.input),
+        Mode::Select(index) => to_select_msg(key, index, &keymap.select),
+        Mode::Selected(index) => {
+            to_selected_msg(key, index, &keymap.selected)
+        }
+        Mode::Move(index) => to_move_msg(key, index, &keymap.move_),
+        Mode::Target(target_state) => {
+            to_target_msg(key, target_state, &keymap.target)
+        }
+        Mode::Wrap(wrap_state) => to_wrap_msg(key, wrap_state, &keymap.wrap),
+        Mode::Macro(macro_state) => {
+            to_macro_msg(key, macro_state, &keymap.macro_)
+        }
+        Mode::Save(save_state) => to_save_msg(key, save_state, &keymap.save),
+        Mode::Help(help_state) => to_help_msg(key, help_state, &keymap.help),
     }
 }
 
-/// Convert a user input event into a Message based on the current `mode`.
-pub fn handle_event(mode: Mode) -> Result<Message> {
-    let event::Event::Key(key) = event::read()? else {
-        return Ok(Message::Continue(mode));
+// Map pasted `text` to a Message based on the current `mode`.
+//
+// A single line is inserted into the current input, while multiple lines are
+// offered as new items.
+fn paste_to_message(mode: Mode, text: String) -> Message {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let text = text.trim_end_matches('\n');
+    let is_multiline = text.contains('\n');
+    match mode {
+        Mode::Input(input_state) if !is_multiline => {
+            let edit = InputEdit::Paste(text.to_string());
+            Message::Input(InputMsg::Edit(edit), input_state)
+        }
+        Mode::Normal
+        | Mode::Select(_)
+        | Mode::Selected(_)
+        | Mode::Input(InputState::Label(_)) if is_multiline => {
+            Message::PasteLines(text.to_string())
+        }
+        _ => Message::Continue(mode),
+    }
+}
+
+// Map a mouse `event` to a Message based on the current `mode`, `state` and
+// the terminal `area`.
+//
+// Pressing a command on the command bar has the same effect as its key.
+// Other than a press, events on the forest are mapped to items as the view was
+// scrolled at the last press, since then.
+fn mouse_to_message(
+    mode: Mode,
+    state: &SessionState,
+    keymap: &Keymap,
+    area: Rect,
+    event: MouseEvent,
+) -> Message {
+    let MouseEvent { kind, column, row, .. } = event;
+    let offset = match (kind, &state.last_press) {
+        (MouseEventKind::Down(_), _) | (_, None) => {
+            view::forest_offset(state, &mode, area)
+        }
+        (_, Some(press)) => Some(press.offset),
     };
-    if key.kind != KeyEventKind::Press {
-        return Ok(Message::Continue(mode));
+    let target = offset.and_then(|offset| {
+        view::forest_index_at(state, area, offset, row)
+            .map(|index| (index, offset))
+    });
+    let mouse_msg = match (kind, target) {
+        (MouseEventKind::Down(MouseButton::Left), Some((index, offset))) => {
+            MouseMsg::Press(MousePress {
+                index,
+                row,
+                offset,
+                time: Instant::now(),
+                dragged: false,
+            })
+        }
+        (MouseEventKind::Down(MouseButton::Left), None) => {
+            let key =
+                view::command_key_at(state, &mode, keymap, area, column, row);
+            let message = match key {
+                Some(key) => key_to_message(mode, key, keymap),
+                None => Message::Continue(mode),
+            };
+            return message;
+        }
+        (MouseEventKind::Drag(MouseButton::Left), _) => MouseMsg::Drag,
+        (MouseEventKind::Up(MouseButton::Left), Some((index, _))) => {
+            MouseMsg::Release(index)
+        }
+        (MouseEventKind::ScrollUp, _) => MouseMsg::ScrollUp,
+        (MouseEventKind::ScrollDown, _) => MouseMsg::ScrollDown,
+        _ => return Message::Continue(mode),
+    };
+    Message::Mouse(mouse_msg, mode)
+}
+
+/// Convert the next user input event into a Message based on the current
+/// `mode`, session `state`, the user `config` and the terminal `area`.
+///
+/// While a macro is played, its keys are used instead of reading an event with
+/// `read_event`, and None is returned when there are no more events.
+/// While one is recorded, each pressed key is kept with its Message, and
+/// pastes, mouse input and keys that cannot be saved stop the recording.
+pub fn handle_event(
+    mode: Mode,
+    state: &SessionState,
+    config: &Config,
+    area: Rect,
+    read_event: impl FnOnce() -> Result<Option<Event>>,
+) -> Result<Option<Message>> {
+    let keymap = &config.keymap;
+    if let Some(key) = state.replay.front() {
+        let message = key_to_message(mode, key.to_event(), keymap);
+        return Ok(Some(Message::Replay(Box::new(message))));
     }
-    Ok(key_to_message(mode, key.code))
+    let Some(event) = read_event()? else {
+        return Ok(None);
+    };
+    let is_recording = state.recording.is_some();
+    let message = match event {
+        Event::Key(key) if key.kind == KeyEventKind::Press => {
+            let binding = KeyBinding::from(key);
+            let message = key_to_message(mode, key, keymap);
+            match (is_recording, binding.name()) {
+                (true, Some(_)) => {
+                    Message::Record(binding, Box::new(message))
+                }
+                (true, None) => Message::Unrecordable(format!("key {binding}")),
+                (false, _) => message,
+            }
+        }
+        Event::Paste(text) => match paste_to_message(mode, text) {
+            Message::Continue(mode) => Message::Continue(mode),
+            _ if is_recording => Message::Unrecordable("pastes".to_string()),
+            message => message,
+        }
+        Event::Mouse(event) => {
+            match mouse_to_message(mode, state, keymap, area, event) {
+                Message::Continue(mode) => Message::Continue(mode),
+                _ if is_recording => {
+                    Message::Unrecordable("mouse input".to_string())
+                }
+                message => message,
+            }
+        }
+        _ => Message::Continue(mode),
+    };
+    Ok(Some(message))
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::update::update;
+
+    use super::*;
+
+    #[test]
+    fn replay_macro_until_not_applicable() {
+        let state = SessionState {
+            cursor: 1,
+            ..SessionState::with_labels(&["a", "b"])
+        };
+        let keys: Vec<KeyBinding> = ["s", "enter", "m", "l", "enter", "esc"]
+            .iter()
+            .filter_map(|key| KeyBinding::parse(key))
+            .collect();
+        let keymap = Keymap::default();
+
+        let state = state.play(&keys, 3);
+        let mut model = Model { state, mode: Mode::Normal };
+        let mut steps = 0;
+        while let Some(key) = model.state.replay.front() {
+            let message = key_to_message(model.mode, key.to_event(), &keymap);
+            let message = Message::Replay(Box::new(message));
+            model = match update(message, model.state) {
+                Command::None(model) => model,
+                _ => panic!("Unexpected IO command"),
+            };
+            steps += 1;
+        }
+        assert_eq!(steps, 10);
+        assert!(matches!(model.mode, Mode::Move(1)));
+        assert_eq!(model.state.root.outline(), vec![(0, "a"), (1, "b")]);
+    }
+}
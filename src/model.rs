@@ -1,31 +1,57 @@
+use std::{
+    borrow::Cow,
+    cmp::min,
+    collections::VecDeque,
+    mem,
+    ops::Range,
+    time::Instant,
+};
+
 use crate::{
-    forest::Node,
+    editor::LineEditor,
+    forest::{Hoisted, IdGen, Item, Node, Placement},
     io::{LoadState, OpenDataFile},
+    keymap::KeyBinding,
+    message::SelectedMsg,
+    util,
 };
 
 /// Action to be confirmed in Confirm mode.
 pub enum ConfirmState {
     NewSession,
     DeleteItem(String, usize),
+    DeleteTree(String, usize),
+    DeleteMarked,
     DeleteFile(LoadState),
+    CreateItems(Vec<(usize, String)>),
 }
 
 /// Action to perform with the user input label string.
+///
+/// Duplicating with a rewrite first asks for the affix to find, then for its
+/// replacement.
 pub enum LabelAction {
     Add,
     Edit(usize),
+    DuplicateFind(usize),
+    DuplicateReplace(usize, String),
+    Wrap(usize, usize),
+    Tag(usize),
 }
 
 /// Current user input label and action to be performed with it.
 pub struct LabelState {
-    pub input: String,
+    pub input: LineEditor,
     pub action: LabelAction,
 }
 
 /// Action to perform after saving.
+///
+/// Continuing keeps the saved file open in the current session.
 pub enum PostSaveAction {
     Load,
     Quit,
+    Continue,
 }
 
 /// Action to perform with the user input filename string.
@@ -44,15 +70,22 @@ pub enum FilenameStatus {
 
 /// Current user input filename with status and next action to be performed.
 pub struct FilenameState {
-    pub input: String,
+    pub input: LineEditor,
     pub action: FilenameAction,
     pub status: FilenameStatus,
 }
 
-/// Input mode state, storing either a label or filename input.
+/// Current command line input, with a message about it to show if any.
+pub struct CommandLineState {
+    pub input: LineEditor,
+    pub info: Option<String>,
+}
+
+/// Input mode state, storing a label, filename or command line input.
 pub enum InputState {
     Label(LabelState),
     Filename(FilenameState),
+    CommandLine(CommandLineState),
 }
 
 /// User's current save choice and subsequent action.
@@ -61,6 +94,50 @@ pub struct SaveState {
     pub post_save: PostSaveAction,
 }
 
+/// Subtrees to be moved in Target mode.
+pub enum MoveSource {
+    Tree(usize),
+    Marked,
+}
+
+/// Subtrees to move from the `source` and currently chosen `target` index.
+pub struct TargetState {
+    pub source: MoveSource,
+    pub target: usize,
+}
+
+/// Range of `count` sibling trees starting at `index` to wrap in a new parent.
+pub struct WrapState {
+    pub index: usize,
+    pub count: usize,
+}
+
+/// Macro action waiting for the name of its register in Macro mode.
+///
+/// A macro is played the given number of times (once if 0).
+pub enum MacroState {
+    Record,
+    Play(usize),
+}
+
+/// A macro being recorded: its `register` name and the `keys` pressed so far.
+pub struct Recording {
+    pub register: char,
+    pub keys: Vec<KeyBinding>,
+}
+
+/// The help shown over the `mode` it was opened from, with the `index` of the
+/// highlighted row out of its number of `rows`.
+pub struct HelpState {
+    pub mode: Box<Mode>,
+    pub index: usize,
+    pub rows: usize,
+}
+
+// Largest number of keys queued to be replayed, or replayed since a key was
+// pressed, ending macros that play themselves without end.
+const MAX_REPLAY: usize = 10_000;
+
 /// Operational modes of the application.
 pub enum Mode {
     Confirm(ConfirmState),
@@ -70,13 +147,59 @@ pub enum Mode {
     Select(usize),
     Selected(usize),
     Move(usize),
+    Target(TargetState),
+    Wrap(WrapState),
+    Macro(MacroState),
     Save(SaveState),
+    Help(HelpState),
+}
+
+/// The last left mouse button press on the forest, at item `index` on screen
+/// `row`, while the forest view was scrolled by `offset`.
+///
+/// Selecting the item scrolls the view, so screen rows are mapped to items
+/// with the `offset` until the next press. The item is only moved on release
+/// if it was `dragged`.
+pub struct MousePress {
+    pub index: usize,
+    pub row: u16,
+    pub offset: usize,
+    pub time: Instant,
+    pub dragged: bool,
 }
 
 /// State that is persistent across modes within a given session.
+///
+/// The `register` holds cut or copied subtrees and is carried between sessions.
+/// Its items keep their IDs when pasted if `register_ids` is set, which is
+/// only until the first paste of items cut in the current session, and are
+/// renumbered otherwise.
+/// The `ids` generator assigns a stable ID to each new item in the `root`.
+/// The `cursor` is the index of the current item, shared between modes and
+/// saved with the file.
+/// While hoisted, the `root` holds only the children of the innermost hoisted
+/// item, and the rest of the forest is kept in `hoists` (outermost first).
+/// The `last_press` is kept to detect double-clicks and drags.
+/// The `count` is being typed to repeat the next motion or move (0 until a
+/// digit is typed), and the `last_action` is the last Selected mode command,
+/// to be repeated on another item.
+/// The keys of a macro being recorded are kept in `recording`, and those of a
+/// macro being played are taken from the front of `replay`, counting them in
+/// `replayed` until a key is pressed.
 pub struct SessionState {
     pub root: Node,
+    pub ids: IdGen,
+    pub cursor: usize,
+    pub hoists: Vec<Hoisted>,
     pub maybe_file: Option<OpenDataFile>,
+    pub register: Node,
+    pub register_ids: bool,
+    pub last_press: Option<MousePress>,
+    pub count: Option<usize>,
+    pub last_action: Option<SelectedMsg>,
+    pub recording: Option<Recording>,
+    pub replay: VecDeque<KeyBinding>,
+    pub replayed: usize,
 }
 
 /// State of the entire application.
@@ -85,24 +208,39 @@ pub struct Model {
     pub mode: Mode,
 }
 
+// Return the name of the `tag` entered, without whitespace around it or a
+// leading `#`.
+fn tag_name(tag: &str) -> &str {
+    tag.trim().trim_start_matches('#').trim_start()
+}
+
 impl LabelState {
-    /// Append a character to the input string, not starting with whitespace.
-    pub fn append(mut self, c: char) -> Self {
-        if !(self.input.is_empty() && c == ' ') {
-            self.input.push(c);
+    /// Return whether the input text is empty, ignoring whitespace unless
+    /// entering an affix, and a leading `#` when entering a tag. The
+    /// replacement of an affix may be empty.
+    pub fn is_empty(&self) -> bool {
+        match self.action {
+            LabelAction::DuplicateFind(_) => self.input.text().is_empty(),
+            LabelAction::DuplicateReplace(..) => false,
+            LabelAction::Tag(_) => tag_name(self.input.text()).is_empty(),
+            _ => self.input.text().trim().is_empty(),
         }
-        self
     }
 
-    /// Pop a character from the input text.
-    pub fn pop(mut self) -> Self {
-        self.input.pop();
-        self
+    /// Return whether the input is an affix, whose whitespace is kept.
+    pub fn is_affix(&self) -> bool {
+        matches!(
+            self.action,
+            LabelAction::DuplicateFind(_) | LabelAction::DuplicateReplace(..)
+        )
     }
 
-    /// Return whether the input text is empty.
-    pub fn is_empty(&self) -> bool {
-        self.input.is_empty()
+    /// Return the input label, trimmed unless it is an affix.
+    pub fn label(&self) -> String {
+        match self.is_affix() {
+            true => self.input.text().to_string(),
+            false => self.input.text().trim().to_string(),
+        }
     }
 
     /// Return the Input mode containing the LabelState.
@@ -112,23 +250,9 @@ impl LabelState {
 }
 
 impl FilenameState {
-    /// Append a character to the input string, not starting with whitespace.
-    pub fn append(mut self, c: char) -> Self {
-        if !(self.input.is_empty() && c == ' ') {
-            self.input.push(c);
-        }
-        self
-    }
-
-    /// Pop a character from the input text.
-    pub fn pop(mut self) -> Self {
-        self.input.pop();
-        self
-    }
-
-    /// Return whether the input text is empty.
+    /// Return whether the input text is empty, ignoring whitespace.
     pub fn is_empty(&self) -> bool {
-        self.input.is_empty()
+        self.input.text().trim().is_empty()
     }
 
     /// Set the filename status.
@@ -144,7 +268,25 @@ impl FilenameState {
 
     /// Return a reference to the trimmed user input.
     pub fn input(&self) -> &str {
-        self.input.trim()
+        self.input.text().trim()
+    }
+}
+
+impl CommandLineState {
+    /// Return whether the input text is empty, ignoring whitespace.
+    pub fn is_empty(&self) -> bool {
+        self.input.text().trim().is_empty()
+    }
+
+    /// Set the message shown about the input.
+    pub fn info(mut self, info: String) -> Self {
+        self.info = Some(info);
+        self
+    }
+
+    /// Return the Input mode containing the CommandLineState.
+    pub fn into_mode(self) -> Mode {
+        Mode::Input(InputState::CommandLine(self))
     }
 }
 
@@ -152,7 +294,7 @@ impl InputState {
     /// Create an InputState to add an item.
     pub fn new_add() -> Self {
         InputState::Label(LabelState {
-            input: String::new(),
+            input: LineEditor::default(),
             action: LabelAction::Add,
         })
     }
@@ -160,15 +302,51 @@ impl InputState {
     /// Create an InputState to edit the `label` of the item at `index`.
     pub fn new_edit(label: String, index: usize) -> Self {
         InputState::Label(LabelState {
-            input: label,
+            input: LineEditor::new(label),
             action: LabelAction::Edit(index),
         })
     }
 
+    /// Create an InputState to enter the label affix to rewrite when
+    /// duplicating the subtree at `index`.
+    pub fn new_duplicate_find(index: usize) -> Self {
+        InputState::Label(LabelState {
+            input: LineEditor::default(),
+            action: LabelAction::DuplicateFind(index),
+        })
+    }
+
+    /// Create an InputState to enter the replacement for the `affix` when
+    /// duplicating the subtree at `index`.
+    pub fn new_duplicate_replace(index: usize, affix: String) -> Self {
+        InputState::Label(LabelState {
+            input: LineEditor::default(),
+            action: LabelAction::DuplicateReplace(index, affix),
+        })
+    }
+
+    /// Create an InputState to enter the label of a new parent wrapping the
+    /// `count` sibling trees starting at `index`.
+    pub fn new_wrap(index: usize, count: usize) -> Self {
+        InputState::Label(LabelState {
+            input: LineEditor::default(),
+            action: LabelAction::Wrap(index, count),
+        })
+    }
+
+    /// Create an InputState to enter a tag for the marked items, returning to
+    /// the item at `index`.
+    pub fn new_tag(index: usize) -> Self {
+        InputState::Label(LabelState {
+            input: LineEditor::default(),
+            action: LabelAction::Tag(index),
+        })
+    }
+
     /// Create an InputState to rename a file.
     pub fn new_rename(load_state: LoadState) -> Self {
         InputState::Filename(FilenameState {
-            input: String::new(),
+            input: LineEditor::default(),
             action: FilenameAction::Rename(load_state),
             status: FilenameStatus::Empty,
         })
@@ -177,12 +355,20 @@ impl InputState {
     /// Create an InputState to save a new file.
     pub fn new_save(post_save: PostSaveAction) -> Self {
         InputState::Filename(FilenameState {
-            input: String::new(),
+            input: LineEditor::default(),
             action: FilenameAction::SaveNew(post_save),
             status: FilenameStatus::Empty,
         })
     }
 
+    /// Create an InputState to enter a command line.
+    pub fn new_command_line() -> Self {
+        InputState::CommandLine(CommandLineState {
+            input: LineEditor::default(),
+            info: None,
+        })
+    }
+
     /// Return whether the user input is valid.
     pub fn is_valid(&self) -> bool {
         match self {
@@ -190,14 +376,16 @@ impl InputState {
             InputState::Filename(filename_state) => {
                 matches!(filename_state.status, FilenameStatus::Valid)
             }
+            InputState::CommandLine(command_line) => !command_line.is_empty(),
         }
     }
 
-    /// Return a reference to the user input string.
-    pub fn input(&self) -> &str {
+    /// Return a reference to the user input line.
+    pub fn input(&self) -> &LineEditor {
         match self {
             InputState::Label(label_state) => &label_state.input,
             InputState::Filename(filename_state) => &filename_state.input,
+            InputState::CommandLine(command_line) => &command_line.input,
         }
     }
 }
@@ -213,6 +401,11 @@ impl SaveState {
         SaveState { save: true, post_save: PostSaveAction::Quit }
     }
 
+    /// Create a SaveState for continuing the session.
+    pub fn new_continue() -> Self {
+        SaveState { save: true, post_save: PostSaveAction::Continue }
+    }
+
     /// Toggle the boolean indicating whether the user intends to save.
     pub fn toggle(mut self) -> Self {
         self.save = !self.save;
@@ -220,15 +413,150 @@ impl SaveState {
     }
 }
 
+impl TargetState {
+    /// Create a TargetState to move the subtree at `source`.
+    pub fn new(source: usize) -> Self {
+        TargetState { source: MoveSource::Tree(source), target: source }
+    }
+
+    /// Create a TargetState to move the marked subtrees, starting at `target`.
+    pub fn new_marked(target: usize) -> Self {
+        TargetState { source: MoveSource::Marked, target }
+    }
+
+    /// Return the index ranges of the subtrees to be moved within `root`.
+    pub fn source_ranges(&self, root: &Node) -> Vec<Range<usize>> {
+        match self.source {
+            MoveSource::Tree(index) => {
+                let range = index..index + root.tree_size(index);
+                vec![range]
+            }
+            MoveSource::Marked => root.marked_trees(),
+        }
+    }
+
+    /// Return whether the target lies outside the subtrees to be moved.
+    pub fn is_valid(&self, root: &Node) -> bool {
+        !self.source_ranges(root)
+            .iter()
+            .any(|range| range.contains(&self.target))
+    }
+
+    /// Append a digit to the target index, given the forest `size`.
+    pub fn append_index(mut self, c: char, size: usize) -> Self {
+        self.target = util::append_index(self.target, c, size);
+        self
+    }
+
+    /// Decrement the target index.
+    pub fn decrement(mut self) -> Self {
+        self.target = self.target.saturating_sub(1);
+        self
+    }
+
+    /// Increment the target index, given the forest `size`.
+    pub fn increment(mut self, size: usize) -> Self {
+        if self.target + 1 < size {
+            self.target += 1;
+        }
+        self
+    }
+
+    /// Return the Target mode containing the TargetState.
+    pub fn into_mode(self) -> Mode {
+        Mode::Target(self)
+    }
+}
+
+impl WrapState {
+    /// Create a WrapState containing only the tree at `index`.
+    pub fn new(index: usize) -> Self {
+        WrapState { index, count: 1 }
+    }
+
+    /// Include one more sibling if there is a `next` one after them.
+    pub fn extend(mut self, next: bool) -> Self {
+        if next {
+            self.count += 1;
+        }
+        self
+    }
+
+    /// Include one less sibling, keeping at least one.
+    pub fn shrink(mut self) -> Self {
+        if self.count > 1 {
+            self.count -= 1;
+        }
+        self
+    }
+
+    /// Return the Wrap mode containing the WrapState.
+    pub fn into_mode(self) -> Mode {
+        Mode::Wrap(self)
+    }
+}
+
+impl HelpState {
+    /// Create a HelpState with `rows` over the `mode`, highlighting the first
+    /// row.
+    pub fn new(mode: Mode, rows: usize) -> Self {
+        HelpState { mode: Box::new(mode), index: 0, rows }
+    }
+
+    /// Highlight the previous row.
+    pub fn decrement(mut self) -> Self {
+        self.index = self.index.saturating_sub(1);
+        self
+    }
+
+    /// Highlight the next row.
+    pub fn increment(mut self) -> Self {
+        if self.index + 1 < self.rows {
+            self.index += 1;
+        }
+        self
+    }
+}
+
 impl SessionState {
-    // Create a SessionState with an empty forest and no saved file.
-    fn new() -> Self {
+    /// Create a SessionState of the `root` forest, whose IDs were generated by
+    /// `ids`, with the cursor on its first item, an empty register and no
+    /// saved file.
+    pub fn new(root: Node, ids: IdGen) -> Self {
         Self {
-            root: Node::Empty,
+            root,
+            ids,
+            cursor: 0,
+            hoists: Vec::new(),
             maybe_file: None,
+            register: Node::Empty,
+            register_ids: false,
+            last_press: None,
+            count: None,
+            last_action: None,
+            recording: None,
+            replay: VecDeque::new(),
+            replayed: 0,
         }
     }
 
+    // Create a SessionState with an empty forest, keeping the `register`.
+    fn empty(register: Node) -> Self {
+        Self { register, ..Self::new(Node::Empty, IdGen::default()) }
+    }
+
+    // Create a SessionState of a forest of roots with the `labels`.
+    #[cfg(test)]
+    pub fn with_labels(labels: &[impl ToString]) -> Self {
+        let mut ids = IdGen::default();
+        let root = labels.iter()
+            .rev()
+            .fold(Node::Empty, |root, label| {
+                root.prepend(Item::new(ids.next_id(), label.to_string()))
+            });
+        Self::new(root, ids)
+    }
+
     // Mark the session state as modified if a saved file exists.
     fn into_changed(mut self) -> Self {
         if let Some(ref mut open_file) = self.maybe_file {
@@ -241,13 +569,129 @@ impl SessionState {
     pub fn is_changed(&self) -> bool {
         match &self.maybe_file {
             Some(open_file) => open_file.is_changed(),
-            None => !matches!(self.root, Node::Empty),
+            None => self.root.size() > 0 || self.is_hoisted(),
+        }
+    }
+
+    /// Return whether the cursor moved from the position saved in the open
+    /// file, if any.
+    pub fn is_cursor_moved(&self) -> bool {
+        match &self.maybe_file {
+            Some(open_file) => open_file.get_cursor() != self.full_forest().1,
+            None => false,
+        }
+    }
+
+    /// Return whether any item is hoisted.
+    pub fn is_hoisted(&self) -> bool {
+        !self.hoists.is_empty()
+    }
+
+    /// Queue the macro `keys` to be played `times` before any keys left to
+    /// replay, or stop playing if too many keys would be queued.
+    pub fn play(mut self, keys: &[KeyBinding], times: usize) -> Self {
+        let queued = keys.len().saturating_mul(times);
+        if self.replay.len().saturating_add(queued) > MAX_REPLAY {
+            self.replay.clear();
+            return self;
+        }
+        for key in keys.repeat(times).into_iter().rev() {
+            self.replay.push_front(key);
+        }
+        self
+    }
+
+    /// Drop the key being replayed from the queue, or stop playing if too
+    /// many keys were replayed since a key was pressed.
+    pub fn pop_replayed(mut self) -> Self {
+        self.replay.pop_front();
+        self.replayed += 1;
+        if self.replayed > MAX_REPLAY {
+            self.replay.clear();
+        }
+        self
+    }
+
+    /// Return the labels of the hoisted items, outermost first.
+    pub fn hoist_labels(&self) -> Vec<&str> {
+        self.hoists.iter()
+            .map(Hoisted::label)
+            .collect()
+    }
+
+    /// Return the labels on the path to the item at `index`, starting from
+    /// the outermost hoisted item.
+    pub fn path_labels(&self, index: usize) -> Vec<&str> {
+        let mut labels = self.hoist_labels();
+        labels.extend(self.root.path_labels(index));
+        labels
+    }
+
+    /// Return the full forest, including the parts outside any hoisted item,
+    /// and the cursor index within it.
+    pub fn full_forest(&self) -> (Cow<'_, Node>, usize) {
+        let mut root = Cow::Borrowed(&self.root);
+        let mut cursor = self.cursor;
+        for hoisted in self.hoists.iter().rev() {
+            let is_empty = root.size() == 0;
+            let (full_root, index) = hoisted.clone().unhoist(root.into_owned());
+            root = Cow::Owned(full_root);
+            cursor = match is_empty {
+                true => index,
+                false => index + 1 + cursor,
+            };
+        }
+        (root, cursor)
+    }
+
+    /// Show only the children of the item at `index`, moving the cursor to
+    /// the first child.
+    pub fn hoist(mut self, index: usize) -> Self {
+        let root = mem::replace(&mut self.root, Node::Empty);
+        let (children, hoisted) = root.hoist(index);
+        self.root = children;
+        self.hoists.push(hoisted);
+        self.cursor = 0;
+        self
+    }
+
+    /// Restore the forest of the innermost hoisted item, moving the cursor
+    /// to that item.
+    pub fn unhoist(mut self) -> Self {
+        if let Some(hoisted) = self.hoists.pop() {
+            let children = mem::replace(&mut self.root, Node::Empty);
+            let (root, index) = hoisted.unhoist(children);
+            self.root = root;
+            self.cursor = index;
         }
+        self
     }
 
-    /// Prepend a top-level `label` at the start of the forest.
+    /// Insert an item with the `label` as the next sibling of the cursor item,
+    /// moving the cursor to it.
     pub fn add(mut self, label: String) -> Self {
-        self.root = self.root.prepend(label);
+        let item = Item::new(self.ids.next_id(), label);
+        let tree = Node::Empty.prepend(item);
+        let (new_root, index) = self.root
+            .insert(self.cursor, tree, Placement::After);
+        self.root = new_root;
+        self.cursor = index;
+        self.into_changed()
+    }
+
+    /// Insert items with the `labels`, nested by their depths, as the next
+    /// siblings of the cursor item, moving the cursor to the first of them.
+    pub fn add_outline(mut self, labels: Vec<(usize, String)>) -> Self {
+        let items: Vec<_> = labels.into_iter()
+            .map(|(depth, label)| {
+                (depth, Item::new(self.ids.next_id(), label))
+            })
+            .collect();
+        let forest = Node::from_outline(items);
+        let (new_root, index) = self.root
+            .insert(self.cursor, forest, Placement::After);
+        self.root = new_root;
+        self.cursor = index;
         self.into_changed()
     }
 
@@ -287,26 +731,197 @@ impl SessionState {
         (self.into_changed(), index)
     }
 
+    /// Move the subtree at `source` to the `placement` relative to `target`.
+    ///
+    /// Return the moved subtree's index (unchanged if the move is refused).
+    pub fn move_to(
+        mut self,
+        source: usize,
+        target: usize,
+        placement: Placement,
+    ) -> (Self, usize) {
+        if self.root.in_subtree(source, target) {
+            return (self, source);
+        }
+        let (new_root, index) = self.root.move_to(source, target, placement);
+        self.root = new_root;
+        (self.into_changed(), index)
+    }
+
     /// Delete the item at `index`.
     pub fn delete(mut self, index: usize) -> Self {
         self.root = self.root.delete(index);
         self.into_changed()
     }
+
+    /// Insert a copy of the subtree at `index` as its next sibling, applying
+    /// `rewrite` to each copied label.
+    ///
+    /// Return the index of the copy.
+    pub fn duplicate(
+        mut self,
+        index: usize,
+        rewrite: impl Fn(String) -> String,
+    ) -> (Self, usize) {
+        let (new_root, index) = self.root
+            .duplicate(index, &mut self.ids, rewrite);
+        self.root = new_root;
+        (self.into_changed(), index)
+    }
+
+    /// Move `count` sibling trees starting at `index` under a new parent with
+    /// the `label`.
+    pub fn wrap(mut self, index: usize, count: usize, label: String) -> Self {
+        let item = Item::new(self.ids.next_id(), label);
+        self.root = self.root.wrap(index, count, item);
+        self.into_changed()
+    }
+
+    /// Delete the subtree at `index`, including all of its descendants.
+    pub fn delete_tree(mut self, index: usize) -> Self {
+        self.root = self.root.delete_tree(index);
+        self.into_changed()
+    }
+
+    /// Move the subtree at `index` into the register.
+    pub fn cut(mut self, index: usize) -> Self {
+        let (new_root, subtree) = self.root.extract(index);
+        self.root = new_root;
+        self.register = subtree.clear_marks();
+        self.register_ids = true;
+        self.into_changed()
+    }
+
+    /// Copy the subtree at `index` into the register.
+    pub fn copy(mut self, index: usize) -> Self {
+        self.register = self.root.copy_tree(index).clear_marks();
+        self.register_ids = false;
+        self
+    }
+
+    /// Toggle whether the item at `index` is marked.
+    pub fn toggle_mark(mut self, index: usize) -> Self {
+        self.root = self.root.toggle_mark(index);
+        self
+    }
+
+    /// Mark the item at `index`.
+    pub fn mark(mut self, index: usize) -> Self {
+        self.root = self.root.set_mark(index, true);
+        self
+    }
+
+    /// Remove all marks.
+    pub fn clear_marks(mut self) -> Self {
+        self.root = self.root.clear_marks();
+        self
+    }
+
+    /// Move the marked subtrees into the register.
+    pub fn cut_marked(mut self) -> Self {
+        let (new_root, extracted) = self.root.extract_marked();
+        self.root = new_root;
+        self.register = extracted.clear_marks();
+        self.register_ids = true;
+        self.into_changed()
+    }
+
+    /// Delete the marked subtrees, including all of their descendants.
+    pub fn delete_marked(mut self) -> Self {
+        let (new_root, _) = self.root.extract_marked();
+        self.root = new_root;
+        self.into_changed()
+    }
+
+    /// Move the marked subtrees to the `placement` relative to `target`.
+    ///
+    /// Return the index of the first moved subtree (the `target` if the move
+    /// is refused).
+    pub fn move_marked(
+        mut self,
+        target: usize,
+        placement: Placement,
+    ) -> (Self, usize) {
+        let (new_root, index) = self.root.move_marked(target, placement);
+        self.root = new_root;
+        (self.into_changed(), index)
+    }
+
+    /// Sort the children of the item at `index` by label.
+    pub fn sort_children(mut self, index: usize) -> Self {
+        self.root = self.root.sort_children(index);
+        self.into_changed()
+    }
+
+    /// Sort the children of each marked item by label.
+    pub fn sort_marked(mut self) -> Self {
+        self.root = self.root.sort_marked();
+        self.into_changed()
+    }
+
+    /// Append the `tag` to the label of each marked item, unless it is
+    /// empty.
+    pub fn tag_marked(mut self, tag: &str) -> Self {
+        let tag = tag_name(tag);
+        if tag.is_empty() {
+            return self;
+        }
+        self.root = self.root.map_marked(&mut |item| {
+            Item { label: format!("{} #{tag}", item.label), ..item }
+        });
+        self.into_changed()
+    }
+
+    /// Insert the register contents at the `placement` relative to `index`.
+    ///
+    /// Items cut in the current session keep their IDs the first time they
+    /// are pasted, and any other pasted copy is given fresh IDs.
+    ///
+    /// Return the index of the pasted subtree (unchanged if nothing to paste).
+    pub fn paste(
+        mut self,
+        index: usize,
+        placement: Placement,
+    ) -> (Self, usize) {
+        if let Node::Empty = self.register {
+            return (self, index);
+        }
+        let register = match mem::take(&mut self.register_ids) {
+            true => self.register.clone(),
+            false => self.register.clone().renumber(&mut self.ids),
+        };
+        let (new_root, index) = self.root.insert(index, register, placement);
+        self.root = new_root;
+        (self.into_changed(), index)
+    }
 }
 
 impl Model {
+    /// Move the cursor to the item focused by the mode, if any, keeping it
+    /// within the forest.
+    pub fn sync_cursor(mut self) -> Self {
+        if let Mode::Select(index) | Mode::Selected(index) | Mode::Move(index) =
+            self.mode
+        {
+            self.state.cursor = index;
+        }
+        let last = self.state.root.size().saturating_sub(1);
+        self.state.cursor = min(self.state.cursor, last);
+        self
+    }
+
     /// Create a default Model for when there are no saved files.
-    pub fn default() -> Self {
+    pub fn default(register: Node) -> Self {
         Model {
-            state: SessionState::new(),
+            state: SessionState::empty(register),
             mode: Mode::Confirm(ConfirmState::NewSession),
         }
     }
 
     /// Create a Model in Load mode containing the `load_state`.
-    pub fn load(load_state: LoadState) -> Self {
+    pub fn load(load_state: LoadState, register: Node) -> Self {
         Model {
-            state: SessionState::new(),
+            state: SessionState::empty(register),
             mode: Mode::Load(load_state),
         }
     }
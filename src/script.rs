@@ -0,0 +1,277 @@
+use std::{fs, path::Path};
+
+use crossterm::event::{
+    Event,
+    KeyCode,
+    KeyEvent,
+    KeyModifiers,
+    MouseButton,
+    MouseEvent,
+    MouseEventKind,
+};
+use ratatui::{backend::TestBackend, buffer::Buffer, Terminal};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    config::Config,
+    forest::Node,
+    io,
+    keymap::KeyBinding,
+    message::{handle_event, Command},
+    model::Model,
+    storage::FsStorage,
+    update::update,
+    view::view,
+};
+
+/// Size of the headless terminal when none is given, as columns and rows.
+pub const DEFAULT_SIZE: (u16, u16) = (80, 24);
+
+// Directory of the frame snapshots within the output directory.
+const SNAPSHOT_DIR: &str = "snapshots";
+// Name of the snapshot of the last frame.
+const FINAL_SNAPSHOT: &str = "final";
+
+/// A step of a key script: an input event, or writing a snapshot of the
+/// frame.
+pub enum Step {
+    Event(Event),
+    Snapshot(String),
+}
+
+// Return the event of pressing the key `code` without modifiers.
+fn press(code: KeyCode) -> Event {
+    Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+// Return the event of the mouse `kind` at `column` and `row`.
+fn mouse(kind: MouseEventKind, column: u16, row: u16) -> Event {
+    Event::Mouse(MouseEvent {
+        kind,
+        column,
+        row,
+        modifiers: KeyModifiers::NONE,
+    })
+}
+
+// Return the `text` with the escapes `\n`, `\t` and `\\` replaced.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some(next @ ('n' | 't' | '\\'))) => {
+                chars.next();
+                unescaped.push(match next {
+                    'n' => '\n',
+                    't' => '\t',
+                    _ => '\\',
+                });
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+// Parse a line of a key script into its steps.
+fn parse_line(line: &str) -> Result<Vec<Step>, String> {
+    let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+    let steps = match command {
+        "key" => args.split_whitespace()
+            .map(|key| match KeyBinding::parse(key) {
+                Some(key) => Ok(Step::Event(Event::Key(key.to_event()))),
+                None => Err(format!("invalid key \"{key}\"")),
+            })
+            .collect::<Result<_, _>>()?,
+        "type" => args.chars()
+            .map(|c| Step::Event(press(KeyCode::Char(c))))
+            .collect(),
+        "paste" => vec![Step::Event(Event::Paste(unescape(args)))],
+        "click" => {
+            let position: Vec<u16> = args.split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|_| "invalid position".to_string())?;
+            let [column, row] = position[..] else {
+                return Err("usage: click <column> <row>".to_string());
+            };
+            let left = MouseButton::Left;
+            vec![
+                Step::Event(mouse(MouseEventKind::Down(left), column, row)),
+                Step::Event(mouse(MouseEventKind::Up(left), column, row)),
+            ]
+        }
+        "snapshot" if !args.trim().is_empty() => {
+            vec![Step::Snapshot(args.trim().to_string())]
+        }
+        "snapshot" => return Err("usage: snapshot <name>".to_string()),
+        _ => return Err(format!("unknown command \"{command}\"")),
+    };
+    Ok(steps)
+}
+
+/// Parse a key script into its steps.
+///
+/// Each line holds one command, and blank lines or lines starting with `#`
+/// are skipped:
+/// - `key <key>...` presses keys written as in the config file, e.g. `ctrl+w`
+/// - `type <text>` types the text after the space
+/// - `paste <text>` pastes the text, where `\n` is a line break
+/// - `click <column> <row>` clicks the left mouse button
+/// - `snapshot <name>` writes the frame to `snapshots/<name>.txt`
+///
+/// Returns a description of the first invalid line on failure.
+pub fn parse(script: &str) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    for (i, line) in script.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let line_steps = parse_line(line.trim_start())
+            .map_err(|error| format!("line {}: {error}", i + 1))?;
+        steps.extend(line_steps);
+    }
+    Ok(steps)
+}
+
+/// Parse a terminal size written as `<columns>x<rows>`, e.g. `80x24`.
+pub fn parse_size(text: &str) -> Result<(u16, u16), String> {
+    text.split_once('x')
+        .and_then(|(columns, rows)| {
+            Some((columns.parse().ok()?, rows.parse().ok()?))
+        })
+        .filter(|&(columns, rows)| columns > 0 && rows > 0)
+        .ok_or_else(|| format!("Invalid terminal size: {text}"))
+}
+
+// Return the text of the frame in the `buffer`, one line per row without
+// trailing spaces, skipping the cells covered by wide characters.
+fn frame_text(buffer: &Buffer) -> String {
+    let mut text = String::new();
+    for cells in buffer.content.chunks(buffer.area.width as usize) {
+        let mut line = String::new();
+        let mut skip = 0;
+        for cell in cells {
+            if skip == 0 {
+                line.push_str(cell.symbol());
+            }
+            skip = skip.max(cell.symbol().width()).saturating_sub(1);
+        }
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+// Write the frame in the `buffer` to the snapshot `name` in the `dir`.
+fn write_snapshot(
+    buffer: &Buffer,
+    dir: &Path,
+    name: &str,
+) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(format!("{name}.txt")), frame_text(buffer))
+}
+
+/// Run the script `steps` on a headless terminal of the `size` (columns and
+/// rows), with the data and config files in the `dir`, isolated from the
+/// user's.
+///
+/// The frame snapshots are written to the `snapshots` directory of `dir`,
+/// ending with the last frame as `final`, exports to its `exports` directory
+/// and copied text to its `clipboard` file.
+/// Returns a description of the problem on failure.
+pub fn run(
+    steps: Vec<Step>,
+    dir: &Path,
+    size: (u16, u16),
+) -> Result<(), String> {
+    let storage = FsStorage::isolated(dir);
+    let config = Config::load(&storage)?;
+    let snapshot_dir = dir.join(SNAPSHOT_DIR);
+    let (width, height) = size;
+    let mut terminal = Terminal::new(TestBackend::new(width, height))
+        .map_err(|error| error.to_string())?;
+    let mut steps = steps.into_iter();
+    let load = Command::Load(Node::Empty);
+    let mut maybe_model = io::execute_command(load, &storage);
+    while let Some(model) = maybe_model {
+        let area = terminal.draw(|frame| view(&model, &config, frame))
+            .map_err(|error| error.to_string())?
+            .area;
+        let buffer = terminal.backend().buffer();
+        let read_event = || {
+            for step in steps.by_ref() {
+                match step {
+                    Step::Event(event) => return Ok(Some(event)),
+                    Step::Snapshot(name) => {
+                        write_snapshot(buffer, &snapshot_dir, &name)?;
+                    }
+                }
+            }
+            Ok(None)
+        };
+        let Model { state, mode } = model;
+        let message = handle_event(mode, &state, &config, area, read_event)
+            .map_err(|error| error.to_string())?;
+        let Some(message) = message else {
+            break;
+        };
+        maybe_model = io::execute_command(update(message, state), &storage);
+    }
+    let buffer = terminal.backend().buffer();
+    write_snapshot(buffer, &snapshot_dir, FINAL_SNAPSHOT)
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use crate::format;
+
+    use super::*;
+
+    #[test]
+    fn parse_script() {
+        let steps = parse("# comment\n\nkey j ctrl+w\ntype a b\nsnapshot one")
+            .unwrap();
+        let error = |script| parse(script).err().unwrap_or_default();
+
+        assert_eq!(steps.len(), 6);
+        assert!(matches!(&steps[5], Step::Snapshot(name) if name == "one"));
+        assert_eq!(unescape(r"a\nb\\n"), "a\nb\\n");
+        assert_eq!(error("key j\nkey ctrl+"), "line 2: invalid key \"ctrl+\"");
+        assert_eq!(error("click 1"), "line 1: usage: click <column> <row>");
+        assert_eq!(error("press j"), "line 1: unknown command \"press\"");
+        assert_eq!(parse_size("100x30"), Ok((100, 30)));
+        assert!(parse_size("100").is_err());
+    }
+
+    #[test]
+    fn run_workflow_script() {
+        let dir = env::temp_dir()
+            .join(format!("elmlog-script-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let script = "key enter\n\
+            key a\ntype Buy milk\nkey enter\n\
+            key a\ntype Call Bob\nkey enter\n\
+            type :w notes\nkey enter\n\
+            snapshot saved\n\
+            key q";
+
+        let result = run(parse(script).unwrap(), &dir, (40, 10));
+        let snapshot = fs::read_to_string(dir.join("snapshots/saved.txt"));
+        let data = fs::read(dir.join("data/notes"));
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(result, Ok(()));
+        let snapshot = snapshot.unwrap();
+        assert!(snapshot.starts_with(" notes | Call Bob\n"));
+        assert_eq!(snapshot.lines().nth(2), Some(" Buy milk"));
+        let (root, _, cursor) = format::deserialize(&data.unwrap()).unwrap();
+        assert_eq!(root.outline(), vec![(0, "Buy milk"), (0, "Call Bob")]);
+        assert_eq!(cursor, 1);
+    }
+}
@@ -0,0 +1,329 @@
+//! Named data files, exports and the clipboard, on the filesystem or in
+//! memory.
+
+use std::{
+    fs::{self, File},
+    io::{self, ErrorKind, Result, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crossterm::{execute, style::Print};
+use fs2::FileExt;
+
+mod memory;
+
+pub use memory::MemoryStorage;
+
+const APP_DIR: &str = "sieve-selector";
+const CONFIG_FILE: &str = "config.toml";
+const MACROS_FILE: &str = "macros.toml";
+const EXPORT_DIR: &str = "exports";
+const CLIPBOARD_FILE: &str = "clipboard";
+
+/// A lock for exclusive access to a data file, held until it is dropped.
+pub trait FileLock {}
+
+/// Storage of the named data files, the saved macros, exported files and the
+/// text copied to the clipboard.
+pub trait Storage {
+    /// Return the names of the data files, sorted.
+    fn list(&self) -> Result<Vec<String>>;
+
+    /// Return whether the data file `name` exists.
+    fn exists(&self, name: &str) -> bool;
+
+    /// Lock the data file `name` for exclusive access, failing if it is
+    /// already locked.
+    fn lock(&self, name: &str) -> Result<Box<dyn FileLock>>;
+
+    /// Return the contents of the data file `name`.
+    fn read(&self, name: &str) -> Result<Vec<u8>>;
+
+    /// Replace the contents of the data file `name` with the `bytes`, creating
+    /// it if missing.
+    ///
+    /// Readers see either the old or the new contents, never a mix.
+    fn write(&self, name: &str, bytes: &[u8]) -> Result<()>;
+
+    /// Rename the data file `from` to `to`.
+    fn rename(&self, from: &str, to: &str) -> Result<()>;
+
+    /// Delete the data file `name`.
+    fn delete(&self, name: &str) -> Result<()>;
+
+    /// Return the text of the saved macros, if any were saved.
+    fn read_macros(&self) -> Result<Option<String>>;
+
+    /// Replace the text of the saved macros.
+    fn write_macros(&self, text: &str) -> Result<()>;
+
+    /// Write the `bytes` to the exported file at `path`, creating or
+    /// replacing it.
+    fn export(&self, path: &Path, bytes: &[u8]) -> Result<()>;
+
+    /// Copy the `text` to the clipboard.
+    fn copy_to_clipboard(&self, text: &str) -> Result<()>;
+}
+
+impl FileLock for File {}
+
+/// Storage in the directories of the data files and of the config files.
+///
+/// Exports are written relative to the working directory and copied text goes
+/// to the terminal's clipboard, unless isolated in an `output` directory.
+pub struct FsStorage {
+    data: PathBuf,
+    config: Option<PathBuf>,
+    output: Option<PathBuf>,
+}
+
+impl FsStorage {
+    /// Return the storage within the user's data and config directories.
+    pub fn user() -> Self {
+        let data_dir = dirs::data_dir()
+            .expect("Failed to identify data directory");
+        FsStorage {
+            data: data_dir.join(APP_DIR),
+            config: dirs::config_dir().map(|dir| dir.join(APP_DIR)),
+            output: None,
+        }
+    }
+
+    /// Return the storage within `dir`, isolated from the user's.
+    ///
+    /// Exports are written relative to its `exports` directory, rejecting
+    /// paths that lead out of it, and copied text to its `clipboard` file.
+    pub fn isolated(dir: &Path) -> Self {
+        FsStorage {
+            data: dir.join("data"),
+            config: Some(dir.join("config")),
+            output: Some(dir.to_path_buf()),
+        }
+    }
+
+    /// Return the path of the config file, if the config directory is known.
+    pub fn config_path(&self) -> Option<PathBuf> {
+        self.config.as_ref().map(|dir| dir.join(CONFIG_FILE))
+    }
+
+    // Return the data directory path, creating any missing directories.
+    fn data_path(&self) -> Result<&Path> {
+        fs::create_dir_all(&self.data)?;
+        Ok(&self.data)
+    }
+
+    // Return the path of the macros file, failing if the config directory is
+    // unknown.
+    fn macros_path(&self) -> Result<PathBuf> {
+        self.config
+            .as_ref()
+            .map(|dir| dir.join(MACROS_FILE))
+            .ok_or_else(|| io::Error::other("Unknown config directory"))
+    }
+}
+
+// Set whether the file's permissions at `path` are read only.
+fn set_read_only(path: &Path, read_only: bool) -> Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(read_only);
+    fs::set_permissions(path, permissions)
+}
+
+// Flush the entries of the directory at `path` to disk.
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> Result<()> {
+    File::open(path)?.sync_all()
+}
+
+// Directories cannot be opened to be flushed on other platforms, which leave
+// flushing the rename to the file system.
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+impl Storage for FsStorage {
+    fn list(&self) -> Result<Vec<String>> {
+        let mut names: Vec<String> = fs::read_dir(self.data_path()?)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| !name.starts_with('.'))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        self.data.join(name).exists()
+    }
+
+    fn lock(&self, name: &str) -> Result<Box<dyn FileLock>> {
+        let file = File::open(self.data.join(name))?;
+        file.try_lock_exclusive()?;
+        Ok(Box::new(file))
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>> {
+        fs::read(self.data.join(name))
+    }
+
+    // The bytes are written to a hidden temporary file and flushed to disk,
+    // before it replaces the data file and the directory entry is flushed,
+    // so a crash leaves either the old or the new contents. Data files are
+    // kept read only against other editors.
+    fn write(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        let dir = self.data_path()?;
+        let temp = dir.join(format!(".{name}.tmp"));
+        if temp.exists() {
+            set_read_only(&temp, false)?;
+        }
+        let mut file = File::create(&temp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        set_read_only(&temp, true)?;
+        fs::rename(&temp, dir.join(name))?;
+        sync_dir(dir)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        fs::rename(self.data.join(from), self.data.join(to))
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        fs::remove_file(self.data.join(name))
+    }
+
+    fn read_macros(&self) -> Result<Option<String>> {
+        match fs::read_to_string(self.macros_path()?) {
+            Ok(text) => Ok(Some(text)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn write_macros(&self, text: &str) -> Result<()> {
+        let path = self.macros_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)
+    }
+
+    // With an output directory, paths that could lead out of it are rejected.
+    fn export(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        let Some(output) = &self.output else {
+            return fs::write(path, bytes);
+        };
+        let is_within = path.components().all(|component| {
+            matches!(component, Component::Normal(_) | Component::CurDir)
+        });
+        if !is_within {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Export path must stay in the output directory",
+            ));
+        }
+        let path = output.join(EXPORT_DIR).join(path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, bytes)
+    }
+
+    // Without an output directory, the text is copied with the OSC 52
+    // terminal escape sequence.
+    fn copy_to_clipboard(&self, text: &str) -> Result<()> {
+        let Some(output) = &self.output else {
+            let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+            return execute!(io::stdout(), Print(sequence));
+        };
+        fs::create_dir_all(output)?;
+        fs::write(output.join(CLIPBOARD_FILE), text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    // Check the behavior shared by every Storage on the empty `storage`.
+    fn check_storage(storage: &dyn Storage) {
+        storage.write("b", b"old").unwrap();
+        storage.write("b", b"new").unwrap();
+        storage.write("a", b"").unwrap();
+        assert_eq!(storage.list().unwrap(), vec!["a", "b"]);
+        assert_eq!(storage.read("b").unwrap(), b"new");
+
+        let lock = storage.lock("b").unwrap();
+        assert!(storage.lock("b").is_err());
+        drop(lock);
+        assert!(storage.lock("b").is_ok());
+
+        let lock = storage.lock("b").unwrap();
+        storage.rename("b", "c").unwrap();
+        assert!(storage.lock("c").is_err());
+        drop(lock);
+        assert!(storage.lock("c").is_ok());
+
+        let lock = storage.lock("c").unwrap();
+        storage.write("c", b"saved").unwrap();
+        let new_lock = storage.lock("c").unwrap();
+        drop(lock);
+        assert!(storage.lock("c").is_err());
+        drop(new_lock);
+
+        let lock = storage.lock("a").unwrap();
+        storage.delete("a").unwrap();
+        storage.write("d", b"").unwrap();
+        storage.rename("d", "a").unwrap();
+        assert!(storage.lock("a").is_ok());
+        drop(lock);
+        storage.delete("a").unwrap();
+        assert!(!storage.exists("b"));
+        assert_eq!(storage.list().unwrap(), vec!["c"]);
+        assert!(storage.read("a").is_err());
+
+        assert_eq!(storage.read_macros().unwrap(), None);
+        storage.write_macros("[macros]").unwrap();
+        assert_eq!(storage.read_macros().unwrap().unwrap(), "[macros]");
+
+        storage.export(Path::new("out/a.md"), b"- a\n").unwrap();
+        storage.copy_to_clipboard("a").unwrap();
+    }
+
+    #[test]
+    fn memory_storage() {
+        let storage = MemoryStorage::new();
+        check_storage(&storage);
+        let exported = storage.exported(Path::new("out/a.md"));
+
+        assert_eq!(exported.unwrap(), b"- a\n");
+        assert_eq!(storage.clipboard().unwrap(), "a");
+    }
+
+    #[test]
+    fn fs_storage() {
+        let dir = env::temp_dir()
+            .join(format!("elmlog-storage-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        check_storage(&FsStorage::isolated(&dir));
+        let read_only = fs::metadata(dir.join("data/c"))
+            .map(|metadata| metadata.permissions().readonly());
+        let storage = FsStorage::isolated(&dir);
+        let outside = dir.join("outside.md");
+        assert!(storage.export(&outside, b"").is_err());
+        assert!(storage.export(Path::new("../outside.md"), b"").is_err());
+        assert!(!outside.exists());
+        let exported = fs::read(dir.join("exports/out/a.md"));
+        let clipboard = fs::read_to_string(dir.join("clipboard"));
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(read_only.unwrap());
+        assert_eq!(exported.unwrap(), b"- a\n");
+        assert_eq!(clipboard.unwrap(), "a");
+    }
+}
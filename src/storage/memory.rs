@@ -0,0 +1,147 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, ErrorKind, Result},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use super::{FileLock, Storage};
+
+// Files of a MemoryStorage, with the ID of the lock on each locked data file,
+// which follows the file when renamed.
+#[derive(Default)]
+struct MemoryFiles {
+    data: BTreeMap<String, Vec<u8>>,
+    locked: BTreeMap<String, u64>,
+    next_lock: u64,
+    macros: Option<String>,
+    exports: BTreeMap<PathBuf, Vec<u8>>,
+    clipboard: Option<String>,
+}
+
+/// Storage keeping the files in memory, for tests and embedding.
+///
+/// Clones share the same files.
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    files: Arc<Mutex<MemoryFiles>>,
+}
+
+// Lock of ID `id` on a data file of a MemoryStorage.
+struct MemoryLock {
+    files: Arc<Mutex<MemoryFiles>>,
+    id: u64,
+}
+
+impl FileLock for MemoryLock {}
+
+impl Drop for MemoryLock {
+    fn drop(&mut self) {
+        if let Ok(mut files) = self.files.lock() {
+            files.locked.retain(|_, id| *id != self.id);
+        }
+    }
+}
+
+// Return the error for the missing data file `name`.
+fn not_found(name: &str) -> io::Error {
+    io::Error::new(ErrorKind::NotFound, format!("No file named {name}"))
+}
+
+impl MemoryStorage {
+    /// Return an empty storage.
+    pub fn new() -> Self {
+        MemoryStorage::default()
+    }
+
+    /// Return the contents of the exported file at `path`, if any.
+    pub fn exported(&self, path: &Path) -> Option<Vec<u8>> {
+        self.files().exports.get(path).cloned()
+    }
+
+    /// Return the text last copied to the clipboard, if any.
+    pub fn clipboard(&self) -> Option<String> {
+        self.files().clipboard.clone()
+    }
+
+    // Return the files, recovering them if another thread panicked.
+    fn files(&self) -> MutexGuard<'_, MemoryFiles> {
+        self.files.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn list(&self) -> Result<Vec<String>> {
+        Ok(self.files().data.keys().cloned().collect())
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        self.files().data.contains_key(name)
+    }
+
+    fn lock(&self, name: &str) -> Result<Box<dyn FileLock>> {
+        let mut files = self.files();
+        if !files.data.contains_key(name) {
+            return Err(not_found(name));
+        }
+        if files.locked.contains_key(name) {
+            return Err(io::Error::new(
+                ErrorKind::WouldBlock,
+                "File is currently locked",
+            ));
+        }
+        let id = files.next_lock;
+        files.next_lock += 1;
+        files.locked.insert(name.to_string(), id);
+        let files = Arc::clone(&self.files);
+        Ok(Box::new(MemoryLock { files, id }))
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>> {
+        self.files().data.get(name).cloned().ok_or_else(|| not_found(name))
+    }
+
+    // The bytes replace the file, as on the filesystem, so a lock on the old
+    // file does not hold the new one.
+    fn write(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        let mut files = self.files();
+        files.locked.remove(name);
+        files.data.insert(name.to_string(), bytes.to_vec());
+        Ok(())
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let mut files = self.files();
+        let bytes = files.data.remove(from).ok_or_else(|| not_found(from))?;
+        files.data.insert(to.to_string(), bytes);
+        if let Some(id) = files.locked.remove(from) {
+            files.locked.insert(to.to_string(), id);
+        }
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        let mut files = self.files();
+        files.locked.remove(name);
+        files.data.remove(name).map(drop).ok_or_else(|| not_found(name))
+    }
+
+    fn read_macros(&self) -> Result<Option<String>> {
+        Ok(self.files().macros.clone())
+    }
+
+    fn write_macros(&self, text: &str) -> Result<()> {
+        self.files().macros = Some(text.to_string());
+        Ok(())
+    }
+
+    fn export(&self, path: &Path, bytes: &[u8]) -> Result<()> {
+        self.files().exports.insert(path.to_path_buf(), bytes.to_vec());
+        Ok(())
+    }
+
+    fn copy_to_clipboard(&self, text: &str) -> Result<()> {
+        self.files().clipboard = Some(text.to_string());
+        Ok(())
+    }
+}
@@ -0,0 +1,276 @@
+use std::env;
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+/// Colors from which the styles of a theme are derived.
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Palette {
+    bg: Color,
+    bg_dark: Color,
+    bg2: Color,
+    fg: Color,
+    fg_dark: Color,
+    gray: Color,
+}
+
+impl Palette {
+    // Apply `f` to each color of the palette.
+    fn map(self, f: impl Fn(Color) -> Color) -> Self {
+        Palette {
+            bg: f(self.bg),
+            bg_dark: f(self.bg_dark),
+            bg2: f(self.bg2),
+            fg: f(self.fg),
+            fg_dark: f(self.fg_dark),
+            gray: f(self.gray),
+        }
+    }
+}
+
+const DARK: Palette = Palette {
+    bg: Color::Rgb(64, 58, 55),
+    bg_dark: Color::Rgb(44, 40, 38),
+    bg2: Color::Rgb(89, 81, 71),
+    fg: Color::Rgb(250, 238, 202),
+    fg_dark: Color::Rgb(248, 232, 180),
+    gray: Color::Rgb(109, 109, 115),
+};
+
+const LIGHT: Palette = Palette {
+    bg: Color::Rgb(250, 244, 230),
+    bg_dark: Color::Rgb(255, 252, 245),
+    bg2: Color::Rgb(226, 214, 194),
+    fg: Color::Rgb(60, 52, 46),
+    fg_dark: Color::Rgb(88, 76, 64),
+    gray: Color::Rgb(150, 145, 140),
+};
+
+const HIGH_CONTRAST: Palette = Palette {
+    bg: Color::Rgb(0, 0, 0),
+    bg_dark: Color::Rgb(0, 0, 0),
+    bg2: Color::Rgb(0, 0, 135),
+    fg: Color::Rgb(255, 255, 255),
+    fg_dark: Color::Rgb(255, 255, 0),
+    gray: Color::Rgb(190, 190, 190),
+};
+
+const ANSI_16: Palette = Palette {
+    bg: Color::Black,
+    bg_dark: Color::Black,
+    bg2: Color::Blue,
+    fg: Color::White,
+    fg_dark: Color::Yellow,
+    gray: Color::DarkGray,
+};
+
+/// Names of the built-in themes.
+pub const THEME_NAMES: [&str; 4] =
+    ["dark", "light", "high-contrast", "16-color"];
+
+/// Return the palette of the built-in theme called `name`, if any.
+pub fn builtin_palette(name: &str) -> Option<Palette> {
+    match name {
+        "dark" => Some(DARK),
+        "light" => Some(LIGHT),
+        "high-contrast" => Some(HIGH_CONTRAST),
+        "16-color" => Some(ANSI_16),
+        _ => None,
+    }
+}
+
+// The 16 ANSI colors with their usual RGB values.
+const ANSI_COLORS: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (128, 0, 0)),
+    (Color::Green, (0, 128, 0)),
+    (Color::Yellow, (128, 128, 0)),
+    (Color::Blue, (0, 0, 128)),
+    (Color::Magenta, (128, 0, 128)),
+    (Color::Cyan, (0, 128, 128)),
+    (Color::Gray, (192, 192, 192)),
+    (Color::DarkGray, (128, 128, 128)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (0, 0, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+// Levels of each RGB component in the 6x6x6 color cube of 256-color
+// terminals.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// Return the squared distance between two RGB colors.
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    [(r1, r2), (g1, g2), (b1, b2)].iter()
+        .map(|&(a, b)| (a as i32 - b as i32).pow(2) as u32)
+        .sum()
+}
+
+// Return the 256-color index closest to the RGB color, from the color cube or
+// the gray ramp.
+fn nearest_indexed(rgb: (u8, u8, u8)) -> u8 {
+    let level = |v: u8| {
+        (0..6)
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - v as i32).abs())
+            .expect("Cube levels are not empty")
+    };
+    let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+    let cube = (CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]);
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray_step = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray_level = 8 + 10 * gray_step;
+    let gray = (gray_level, gray_level, gray_level);
+    match distance(rgb, gray) < distance(rgb, cube) {
+        true => 232 + gray_step,
+        false => 16 + 36 * r as u8 + 6 * g as u8 + b as u8,
+    }
+}
+
+// Return the ANSI color closest to the RGB color.
+fn nearest_ansi(rgb: (u8, u8, u8)) -> Color {
+    ANSI_COLORS.iter()
+        .min_by_key(|(_, ansi)| distance(rgb, *ansi))
+        .map(|(color, _)| *color)
+        .expect("ANSI colors are not empty")
+}
+
+/// Colors supported by the terminal.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorSupport {
+    NoColor,
+    Ansi16,
+    Indexed256,
+    TrueColor,
+}
+
+impl ColorSupport {
+    /// Detect the supported colors from the environment, honoring `NO_COLOR`.
+    pub fn detect() -> Self {
+        let var = |name| env::var(name).unwrap_or_default();
+        let (no_color, colorterm) = (var("NO_COLOR"), var("COLORTERM"));
+        ColorSupport::from_vars(&no_color, &colorterm, &var("TERM"))
+    }
+
+    // Return the supported colors given the `NO_COLOR`, `COLORTERM` and
+    // `TERM` environment variables.
+    fn from_vars(no_color: &str, colorterm: &str, term: &str) -> Self {
+        if !no_color.is_empty() {
+            ColorSupport::NoColor
+        } else if colorterm == "truecolor" || colorterm == "24bit" {
+            ColorSupport::TrueColor
+        } else if term.contains("256color") {
+            ColorSupport::Indexed256
+        } else {
+            ColorSupport::Ansi16
+        }
+    }
+
+    // Return the closest supported color to `color`.
+    fn convert(self, color: Color) -> Color {
+        match (self, color) {
+            (ColorSupport::Indexed256, Color::Rgb(r, g, b)) => {
+                Color::Indexed(nearest_indexed((r, g, b)))
+            }
+            (ColorSupport::Ansi16, Color::Rgb(r, g, b)) => {
+                nearest_ansi((r, g, b))
+            }
+            _ => color,
+        }
+    }
+}
+
+/// Styles of each part of the UI.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Theme {
+    pub default: Style,
+    pub default_hl: Style,
+    pub accent: Style,
+    pub tree: Style,
+    pub tree_hl: Style,
+    pub marked: Style,
+    pub cmd_key: Style,
+    pub cmd_name: Style,
+}
+
+// Theme without colors, used when `NO_COLOR` is set.
+const MONOCHROME: Theme = Theme {
+    default: Style::new(),
+    default_hl: Style::new().add_modifier(Modifier::REVERSED),
+    accent: Style::new().add_modifier(Modifier::REVERSED),
+    tree: Style::new().add_modifier(Modifier::DIM),
+    tree_hl: Style::new()
+        .add_modifier(Modifier::REVERSED)
+        .add_modifier(Modifier::DIM),
+    marked: Style::new().add_modifier(Modifier::UNDERLINED),
+    cmd_key: Style::new()
+        .add_modifier(Modifier::BOLD)
+        .remove_modifier(Modifier::REVERSED),
+    cmd_name: Style::new().add_modifier(Modifier::ITALIC),
+};
+
+impl Theme {
+    /// Create the theme of the `palette`, with its colors converted to the
+    /// closest ones in the color `support`.
+    pub fn new(palette: Palette, support: ColorSupport) -> Self {
+        if support == ColorSupport::NoColor {
+            return MONOCHROME;
+        }
+        let Palette { bg, bg_dark, bg2, fg, fg_dark, gray } =
+            palette.map(|color| support.convert(color));
+        let default = Style::new().fg(fg).bg(bg);
+        let accent = Style::new().fg(fg).bg(bg2);
+        Theme {
+            default,
+            default_hl: Style::new().fg(bg_dark).bg(fg),
+            accent,
+            tree: Style::new().fg(gray).bg(bg),
+            tree_hl: Style::new().fg(gray).bg(fg),
+            marked: default.add_modifier(Modifier::UNDERLINED),
+            cmd_key: Style::new().fg(bg_dark).bg(fg_dark)
+                .add_modifier(Modifier::BOLD),
+            cmd_name: accent.add_modifier(Modifier::ITALIC),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_color_support() {
+        let support = ColorSupport::from_vars;
+
+        assert_eq!(support("1", "truecolor", ""), ColorSupport::NoColor);
+        assert_eq!(support("", "24bit", ""), ColorSupport::TrueColor);
+        assert_eq!(support("", "", "xterm-256color"), ColorSupport::Indexed256);
+        assert_eq!(support("", "", "xterm"), ColorSupport::Ansi16);
+    }
+
+    #[test]
+    fn convert_rgb_colors() {
+        let convert = |support: ColorSupport, rgb| support.convert(rgb);
+
+        assert_eq!(
+            convert(ColorSupport::Indexed256, Color::Rgb(255, 0, 0)),
+            Color::Indexed(196),
+        );
+        assert_eq!(
+            convert(ColorSupport::Indexed256, Color::Rgb(64, 58, 55)),
+            Color::Indexed(237),
+        );
+        assert_eq!(
+            convert(ColorSupport::Ansi16, Color::Rgb(250, 238, 202)),
+            Color::White,
+        );
+        assert_eq!(
+            convert(ColorSupport::Ansi16, Color::Blue),
+            Color::Blue,
+        );
+    }
+}
@@ -1,34 +1,61 @@
+use std::{
+    cmp::min,
+    time::Duration,
+};
+
 use crate::{
+    cmdline::{self, Action},
+    editor::LineEditor,
+    forest::{Motion, Placement},
     io::LoadState,
     message::{
+        BulkMsg,
         Command,
+        HelpMsg,
         InputEdit,
         InputMsg,
         LoadMsg,
+        MacroMsg,
         Message,
+        MouseMsg,
         MoveMsg,
         NormalMsg,
         SaveMsg,
         SelectedMsg,
         SelectMsg,
+        TargetMsg,
+        WrapMsg,
     },
     model::{
+        CommandLineState,
         ConfirmState,
         FilenameAction,
         FilenameState,
         FilenameStatus,
+        HelpState,
         InputState,
         LabelAction,
         LabelState,
+        MacroState,
         Mode,
         Model,
+        MousePress,
+        MoveSource,
         PostSaveAction,
+        Recording,
         SaveState,
         SessionState,
+        TargetState,
+        WrapState,
     },
     util,
 };
 
+// Longest delay between the two presses of a double-click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+// Number of items moved per scroll wheel step.
+const SCROLL_STEP: usize = 3;
+
 // Update the Model based on a Confirm mode message.
 fn update_confirm(
     confirm: bool,
@@ -42,14 +69,29 @@ fn update_confirm(
                 state = state.delete(index);
                 Mode::Normal
             }
+            ConfirmState::DeleteTree(_, index) => {
+                state = state.delete_tree(index);
+                Mode::Normal
+            }
+            ConfirmState::DeleteMarked => {
+                state = state.delete_marked();
+                Mode::Normal
+            }
             ConfirmState::DeleteFile(load_state) => {
-                return Command::DeleteFile(load_state);
+                return Command::DeleteFile(load_state, state.register);
+            }
+            ConfirmState::CreateItems(labels) => {
+                state = state.add_outline(labels);
+                Mode::Normal
             }
         }
         false => match confirm_state {
             ConfirmState::NewSession => Mode::Confirm(ConfirmState::NewSession),
             ConfirmState::DeleteItem(..) => Mode::Normal,
+            ConfirmState::DeleteTree(..) => Mode::Normal,
+            ConfirmState::DeleteMarked => Mode::Normal,
             ConfirmState::DeleteFile(load_state) => Mode::Load(load_state),
+            ConfirmState::CreateItems(_) => Mode::Normal,
         }
     };
     Command::None(Model { state, mode })
@@ -66,8 +108,8 @@ fn update_load(
         LoadMsg::Decrement => Mode::Load(load_state.decrement()),
         LoadMsg::Increment => Mode::Load(load_state.increment()),
         LoadMsg::Open => {
-            let file_entry = load_state.move_file_entry();
-            return Command::InitSession(file_entry);
+            let filename = load_state.move_filename();
+            return Command::InitSession(filename, state.register);
         }
         LoadMsg::New => Mode::Normal,
         LoadMsg::Rename => Mode::Input(InputState::new_rename(load_state)),
@@ -77,26 +119,90 @@ fn update_load(
     Command::None(Model { state, mode })
 }
 
+// Return the Command to leave an unchanged session with the `post_save`
+// action, saving the file only if the cursor moved.
+fn leave_session(state: SessionState, post_save: PostSaveAction) -> Command {
+    if state.is_cursor_moved() {
+        return Command::Save(state, post_save);
+    }
+    match post_save {
+        PostSaveAction::Load => Command::Load(state.register),
+        PostSaveAction::Quit => Command::Quit,
+        PostSaveAction::Continue => {
+            Command::None(Model { state, mode: Mode::Normal })
+        }
+    }
+}
+
 // Update the Model based on a Normal mode message.
-fn update_normal(msg: NormalMsg, state: SessionState) -> Command {
+fn update_normal(msg: NormalMsg, mut state: SessionState) -> Command {
     let mode = match msg {
         NormalMsg::Input => Mode::Input(InputState::new_add()),
         NormalMsg::Select => match state.root.size() > 0 {
-            true => Mode::Select(0),
+            true => Mode::Select(state.cursor),
             false => Mode::Normal,
         }
+        NormalMsg::Paste => {
+            let cursor = state.cursor;
+            let (mut state, index) = state.paste(cursor, Placement::After);
+            state.cursor = index;
+            return Command::None(Model { state, mode: Mode::Normal });
+        }
+        NormalMsg::Unhoist => {
+            let state = state.unhoist();
+            return Command::None(Model { state, mode: Mode::Normal });
+        }
+        NormalMsg::Command => Mode::Input(InputState::new_command_line()),
+        NormalMsg::Record => match state.recording.take() {
+            Some(Recording { register, keys }) => {
+                let model = Model { state, mode: Mode::Normal };
+                return Command::SaveMacro(model, register, keys);
+            }
+            None => Mode::Macro(MacroState::Record),
+        }
+        NormalMsg::Play => Mode::Macro(MacroState::Play(0)),
         NormalMsg::Load => match state.is_changed() {
             true => Mode::Save(SaveState::new_load()),
-            false => return Command::Load,
+            false => return leave_session(state, PostSaveAction::Load),
         }
         NormalMsg::Quit => match state.is_changed() {
             true => Mode::Save(SaveState::new_quit()),
-            false => return Command::Quit,
+            false => return leave_session(state, PostSaveAction::Quit),
         }
     };
     Command::None(Model { state, mode })
 }
 
+// Apply the `edit` to the input `line`, not starting it with whitespace.
+fn edit_line(line: LineEditor, edit: InputEdit) -> LineEditor {
+    match edit {
+        InputEdit::Insert(' ') if line.is_empty() => line,
+        InputEdit::Paste(text) if line.is_empty() => {
+            line.insert_str(text.trim_start())
+        }
+        edit => edit_text(line, edit),
+    }
+}
+
+// Apply the `edit` to the input `line`, keeping any whitespace.
+fn edit_text(line: LineEditor, edit: InputEdit) -> LineEditor {
+    match edit {
+        InputEdit::Insert(c) => line.insert(c),
+        InputEdit::Paste(text) => line.insert_str(&text),
+        InputEdit::Backspace => line.backspace(),
+        InputEdit::Delete => line.delete(),
+        InputEdit::Left => line.left(),
+        InputEdit::Right => line.right(),
+        InputEdit::WordLeft => line.word_left(),
+        InputEdit::WordRight => line.word_right(),
+        InputEdit::Home => line.home(),
+        InputEdit::End => line.end(),
+        InputEdit::KillWord => line.kill_word(),
+        InputEdit::KillToStart => line.kill_to_start(),
+        InputEdit::KillToEnd => line.kill_to_end(),
+    }
+}
+
 // Update the Model based on an Input mode label editing message.
 fn update_label(
     msg: InputMsg,
@@ -104,20 +210,46 @@ fn update_label(
     state: SessionState,
 ) -> Command {
     let label_state = match msg {
-        InputMsg::Edit(edit) => match edit {
-            InputEdit::Append(c) => label_state.append(c),
-            InputEdit::PopChar => label_state.pop(),
+        InputMsg::Edit(edit) => {
+            let input = match label_state.is_affix() {
+                true => edit_text(label_state.input, edit),
+                false => edit_line(label_state.input, edit),
+            };
+            LabelState { input, ..label_state }
         }
         InputMsg::Submit => match label_state.is_empty() {
             true => label_state,
             false => {
-                let LabelState { input, action } = label_state;
-                let label = input.trim().to_string();
-                let state = match action {
-                    LabelAction::Add => state.add(label),
-                    LabelAction::Edit(index) => state.edit(index, label),
+                let label = label_state.label();
+                let action = label_state.action;
+                let model = match action {
+                    LabelAction::Add => {
+                        Model { state: state.add(label), mode: Mode::Normal }
+                    }
+                    LabelAction::Edit(index) => {
+                        let state = state.edit(index, label);
+                        Model { state, mode: Mode::Normal }
+                    }
+                    LabelAction::DuplicateFind(index) => {
+                        let input_state =
+                            InputState::new_duplicate_replace(index, label);
+                        Model { state, mode: Mode::Input(input_state) }
+                    }
+                    LabelAction::DuplicateReplace(index, affix) => {
+                        let (state, index) = state.duplicate(index, |l| {
+                            util::rewrite_affix(l, &affix, &label)
+                        });
+                        Model { state, mode: Mode::Select(index) }
+                    }
+                    LabelAction::Wrap(index, count) => {
+                        let state = state.wrap(index, count, label);
+                        Model { state, mode: Mode::Select(index) }
+                    }
+                    LabelAction::Tag(index) => {
+                        let state = state.tag_marked(&label);
+                        Model { state, mode: Mode::Select(index) }
+                    }
                 };
-                let model = Model { state, mode: Mode::Normal };
                 return Command::None(model);
             }
         }
@@ -125,6 +257,7 @@ fn update_label(
             let model = Model { state, mode: Mode::Normal };
             return Command::None(model);
         }
+        InputMsg::Complete => label_state,
     };
     let mode = label_state.into_mode();
     Command::None(Model { state, mode })
@@ -138,9 +271,9 @@ fn update_filename(
 ) -> Command {
     let filename_state = match msg {
         InputMsg::Edit(edit) => {
-            let filename_state = match edit {
-                InputEdit::Append(c) => filename_state.append(c),
-                InputEdit::PopChar => filename_state.pop(),
+            let filename_state = FilenameState {
+                input: edit_line(filename_state.input, edit),
+                ..filename_state
             };
             match filename_state.is_empty() {
                 true => filename_state.status(FilenameStatus::Empty),
@@ -150,7 +283,7 @@ fn update_filename(
         InputMsg::Submit => match filename_state.is_empty() {
             true => filename_state.status(FilenameStatus::Empty),
             false => {
-                let filename = filename_state.input;
+                let filename = filename_state.input.into_text();
                 match filename_state.action {
                     FilenameAction::Rename(load_state) => {
                         return Command::Rename(state, filename, load_state);
@@ -168,31 +301,166 @@ fn update_filename(
             };
             return Command::None(Model { state, mode });
         }
+        InputMsg::Complete => filename_state,
     };
     let mode = filename_state.into_mode();
     Command::None(Model { state, mode })
 }
 
-// Update the Model based on a Select mode message.
-fn update_select(msg: SelectMsg, index: usize, state: SessionState) -> Command {
+// Update the Model based on an Input mode command line message.
+//
+// A submitted command that fails to parse stays on the command line, showing
+// the error.
+fn update_command_line(
+    msg: InputMsg,
+    command_line: CommandLineState,
+    state: SessionState,
+) -> Command {
+    let command_line = match msg {
+        InputMsg::Edit(edit) => CommandLineState {
+            input: edit_line(command_line.input, edit),
+            info: None,
+        },
+        InputMsg::Complete => {
+            let (text, info) = cmdline::complete(command_line.input.text());
+            CommandLineState { input: LineEditor::new(text), info }
+        }
+        InputMsg::Submit if command_line.is_empty() => command_line,
+        InputMsg::Submit => {
+            match cmdline::parse(command_line.input.text(), &state) {
+                Ok(action) => return run_action(action, command_line, state),
+                Err(error) => command_line.info(error),
+            }
+        }
+        InputMsg::Cancel => {
+            return Command::None(Model { state, mode: Mode::Normal });
+        }
+    };
+    let mode = command_line.into_mode();
+    Command::None(Model { state, mode })
+}
+
+// Return the Command performing a command line `action`, keeping the
+// `command_line` to show any IO error.
+fn run_action(
+    action: Action,
+    command_line: CommandLineState,
+    state: SessionState,
+) -> Command {
+    match action {
+        Action::Message(message) => update_mode(message, state),
+        Action::Export(format, path) => {
+            Command::Export(state, command_line, format, path)
+        }
+        Action::Rename(filename) => {
+            Command::RenameOpen(state, command_line, filename)
+        }
+        Action::SaveNew(filename) => {
+            Command::SaveNew(state, filename, PostSaveAction::Continue)
+        }
+    }
+}
+
+// Return the Select mode (or Normal mode if the forest is empty) with the
+// `index` limited to the forest size.
+fn select_within(index: usize, state: &SessionState) -> Mode {
+    match state.root.size() {
+        0 => Mode::Normal,
+        size => Mode::Select(min(index, size - 1)),
+    }
+}
+
+// Update the Model based on a bulk operation on the marked items.
+fn update_bulk(msg: BulkMsg, index: usize, state: SessionState) -> Command {
+    if state.root.mark_count() == 0 {
+        return Command::None(Model { state, mode: Mode::Select(index) });
+    }
+    let (state, mode) = match msg {
+        BulkMsg::Cut => {
+            let state = state.cut_marked();
+            let mode = select_within(index, &state);
+            (state, mode)
+        }
+        BulkMsg::Delete => (state, Mode::Confirm(ConfirmState::DeleteMarked)),
+        BulkMsg::Move => (state, TargetState::new_marked(index).into_mode()),
+        BulkMsg::Sort => {
+            let id = state.root.find_id(index);
+            let state = state.sort_marked();
+            let index = state.root.find_index(id).unwrap_or(index);
+            (state, Mode::Select(index))
+        }
+        BulkMsg::Tag => (state, Mode::Input(InputState::new_tag(index))),
+    };
+    Command::None(Model { state, mode })
+}
+
+// Update the Model based on a Select mode message, repeating motions by the
+// count typed before them.
+fn update_select(
+    msg: SelectMsg,
+    index: usize,
+    mut state: SessionState,
+) -> Command {
+    let count = state.count.take();
+    let times = count.map_or(1, |count| count.max(1));
     let mode = match msg {
-        SelectMsg::Append(c) => {
-            let i = util::append_index(index, c, state.root.size());
-            Mode::Select(i)
-        }
-        SelectMsg::Decrement => {
-            match index > 0 {
-                true => Mode::Select(index - 1),
-                false => Mode::Select(index),
+        SelectMsg::Append(c) => match count {
+            Some(count) => {
+                state.count = Some(util::append_count(count, c));
+                Mode::Select(index)
+            }
+            None => {
+                let i = util::append_index(index, c, state.root.size());
+                Mode::Select(i)
             }
         }
+        SelectMsg::Decrement => Mode::Select(index.saturating_sub(times)),
         SelectMsg::Increment => {
-            match index + 1 < state.root.size() {
-                true => Mode::Select(index + 1),
-                false => Mode::Select(index),
+            let last = state.root.size().saturating_sub(1);
+            Mode::Select(min(index + times, last))
+        }
+        SelectMsg::Jump(motion) => {
+            let index = (0..times)
+                .fold(index, |index, _| state.root.jump(index, motion));
+            Mode::Select(index)
+        }
+        SelectMsg::Count => {
+            state.count = Some(0);
+            Mode::Select(index)
+        }
+        SelectMsg::Repeat => match state.last_action.clone() {
+            Some(msg) => return update_selected(msg, index, state),
+            None => Mode::Select(index),
+        }
+        SelectMsg::Unhoist => match state.is_hoisted() {
+            true => {
+                state = state.unhoist();
+                Mode::Select(state.cursor)
             }
+            false => Mode::Select(index),
+        }
+        SelectMsg::ToggleMark => {
+            state = state.toggle_mark(index);
+            Mode::Select(index)
+        }
+        SelectMsg::MarkDown => {
+            let next = min(index + 1, state.root.size() - 1);
+            state = state.mark(index).mark(next);
+            Mode::Select(next)
+        }
+        SelectMsg::MarkUp => {
+            let prev = index.saturating_sub(1);
+            state = state.mark(index).mark(prev);
+            Mode::Select(prev)
         }
+        SelectMsg::ClearMarks => {
+            state = state.clear_marks();
+            Mode::Select(index)
+        }
+        SelectMsg::Bulk(msg) => return update_bulk(msg, index, state),
+        SelectMsg::Command => Mode::Input(InputState::new_command_line()),
         SelectMsg::Confirm => Mode::Selected(index),
+        SelectMsg::Cancel => Mode::Normal,
     };
     Command::None(Model { state, mode })
 }
@@ -201,8 +469,11 @@ fn update_select(msg: SelectMsg, index: usize, state: SessionState) -> Command {
 fn update_selected(
     msg: SelectedMsg,
     index: usize,
-    state: SessionState,
+    mut state: SessionState,
 ) -> Command {
+    if !matches!(msg, SelectedMsg::Command | SelectedMsg::Cancel) {
+        state.last_action = Some(msg.clone());
+    }
     let label = state.root.find_label(index);
     let mode = match msg {
         SelectedMsg::Edit => {
@@ -212,40 +483,176 @@ fn update_selected(
         SelectedMsg::Delete => {
             Mode::Confirm(ConfirmState::DeleteItem(label, index))
         }
+        SelectedMsg::DeleteTree => {
+            Mode::Confirm(ConfirmState::DeleteTree(label, index))
+        }
+        SelectedMsg::Cut => {
+            let state = state.cut(index);
+            let mode = select_within(index, &state);
+            return Command::None(Model { state, mode });
+        }
+        SelectedMsg::Copy => {
+            let state = state.copy(index);
+            return Command::None(Model { state, mode: Mode::Select(index) });
+        }
+        SelectedMsg::CopyPath => {
+            let path = state
+                .path_labels(index)
+                .join(util::PATH_SEPARATOR);
+            let model = Model { state, mode: Mode::Select(index) };
+            return Command::Clipboard(model, path);
+        }
+        SelectedMsg::Paste(placement) => {
+            let (state, index) = state.paste(index, placement);
+            return Command::None(Model { state, mode: Mode::Select(index) });
+        }
+        SelectedMsg::MoveTo => TargetState::new(index).into_mode(),
+        SelectedMsg::Duplicate => {
+            let (state, index) = state.duplicate(index, |label| label);
+            return Command::None(Model { state, mode: Mode::Select(index) });
+        }
+        SelectedMsg::DuplicateRewrite => {
+            Mode::Input(InputState::new_duplicate_find(index))
+        }
+        SelectedMsg::Wrap => WrapState::new(index).into_mode(),
+        SelectedMsg::Hoist => {
+            let state = state.hoist(index);
+            let mode = select_within(0, &state);
+            return Command::None(Model { state, mode });
+        }
+        SelectedMsg::Sort => {
+            let state = state.sort_children(index);
+            return Command::None(Model { state, mode: Mode::Select(index) });
+        }
+        SelectedMsg::Command => Mode::Input(InputState::new_command_line()),
+        SelectedMsg::Cancel => Mode::Normal,
     };
     Command::None(Model { state, mode })
 }
 
-// Update the Model based on a Move mode message.
+// Update the Model based on a Move mode message, repeating moves by the count
+// typed before them.
 fn update_move(
     msg: MoveMsg,
     index: usize,
+    mut state: SessionState,
+) -> Command {
+    let count = state.count.take();
+    let step = match msg {
+        MoveMsg::Append(c) => {
+            state.count = count.map(|count| util::append_count(count, c));
+            return Command::None(Model { state, mode: Mode::Move(index) });
+        }
+        MoveMsg::Count => {
+            state.count = Some(0);
+            return Command::None(Model { state, mode: Mode::Move(index) });
+        }
+        MoveMsg::Forward => SessionState::move_forward,
+        MoveMsg::Backward => SessionState::move_backward,
+        MoveMsg::Promote => SessionState::promote,
+        MoveMsg::Demote => SessionState::demote,
+        MoveMsg::Done => {
+            return Command::None(Model { state, mode: Mode::Select(index) });
+        }
+        MoveMsg::Cancel => {
+            return Command::None(Model { state, mode: Mode::Normal });
+        }
+    };
+    let times = count.map_or(1, |count| count.max(1));
+    let (state, index) = (0..times)
+        .fold((state, index), |(state, index), _| step(state, index));
+    Command::None(Model { state, mode: Mode::Move(index) })
+}
+
+// Update the Model based on a Target mode message.
+fn update_target(
+    msg: TargetMsg,
+    target_state: TargetState,
+    state: SessionState,
+) -> Command {
+    let size = state.root.size();
+    let target_state = match msg {
+        TargetMsg::Append(c) => target_state.append_index(c, size),
+        TargetMsg::Decrement => target_state.decrement(),
+        TargetMsg::Increment => target_state.increment(size),
+        TargetMsg::Cancel => {
+            return Command::None(Model { state, mode: Mode::Normal });
+        }
+        TargetMsg::Place(placement) => {
+            match target_state.is_valid(&state.root) {
+                false => target_state,
+                true => {
+                    let TargetState { source, target } = target_state;
+                    let (state, index) = match source {
+                        MoveSource::Tree(source) => {
+                            state.move_to(source, target, placement)
+                        }
+                        MoveSource::Marked => {
+                            state.move_marked(target, placement)
+                        }
+                    };
+                    let mode = Mode::Select(index);
+                    return Command::None(Model { state, mode });
+                }
+            }
+        }
+    };
+    let mode = target_state.into_mode();
+    Command::None(Model { state, mode })
+}
+
+// Update the Model based on a Wrap mode message.
+fn update_wrap(
+    msg: WrapMsg,
+    wrap_state: WrapState,
     state: SessionState,
 ) -> Command {
-    let model = match msg {
-        MoveMsg::Forward => {
-            let (state, index) = state.move_forward(index);
-            let mode = Mode::Move(index);
-            Model { state, mode }
-        }
-        MoveMsg::Backward => {
-            let (state, index) = state.move_backward(index);
-            let mode = Mode::Move(index);
-            Model { state, mode }
-        }
-        MoveMsg::Promote => {
-            let (state, index) = state.promote(index);
-            let mode = Mode::Move(index);
-            Model { state, mode }
-        }
-        MoveMsg::Demote => {
-            let (state, index) = state.demote(index);
-            let mode = Mode::Move(index);
-            Model { state, mode }
-        }
-        MoveMsg::Done => Model { state, mode: Mode::Select(index) },
+    let mode = match msg {
+        WrapMsg::Extend => {
+            let WrapState { index, count } = &wrap_state;
+            let next = state.root.has_more_siblings(*index, *count);
+            wrap_state.extend(next).into_mode()
+        }
+        WrapMsg::Shrink => wrap_state.shrink().into_mode(),
+        WrapMsg::Confirm => {
+            let WrapState { index, count } = wrap_state;
+            Mode::Input(InputState::new_wrap(index, count))
+        }
+        WrapMsg::Cancel => Mode::Normal,
+    };
+    Command::None(Model { state, mode })
+}
+
+// Update the Model based on a Macro mode message, starting to record or play
+// once a register name is typed.
+fn update_macro(
+    msg: MacroMsg,
+    macro_state: MacroState,
+    mut state: SessionState,
+) -> Command {
+    let mode = match (msg, macro_state) {
+        (MacroMsg::Append(c), MacroState::Play(count))
+            if c.is_ascii_digit() =>
+        {
+            Mode::Macro(MacroState::Play(util::append_count(count, c)))
+        }
+        (MacroMsg::Append(register), MacroState::Record)
+            if register.is_ascii_alphabetic() =>
+        {
+            let keys = Vec::new();
+            state.recording = Some(Recording { register, keys });
+            Mode::Normal
+        }
+        (MacroMsg::Append(register), MacroState::Play(count))
+            if register.is_ascii_alphabetic() =>
+        {
+            let model = Model { state, mode: Mode::Normal };
+            return Command::PlayMacro(model, register, count.max(1));
+        }
+        (MacroMsg::Append(_), macro_state) => Mode::Macro(macro_state),
+        (MacroMsg::Cancel, _) => Mode::Normal,
     };
-    Command::None(model)
+    Command::None(Model { state, mode })
 }
 
 // Update the Model based on a Save mode message.
@@ -256,6 +663,7 @@ fn update_save(
 ) -> Command {
     let mode = match msg {
         SaveMsg::Toggle => Mode::Save(save_state.toggle()),
+        SaveMsg::Cancel => Mode::Normal,
         SaveMsg::Confirm => {
             let SaveState { save, post_save } = save_state;
             match save {
@@ -263,9 +671,12 @@ fn update_save(
                     Some(_) => return Command::Save(state, post_save),
                     None => Mode::Input(InputState::new_save(post_save))
                 }
-                false => match post_save {
-                    PostSaveAction::Load => return Command::Load,
-                    PostSaveAction::Quit => return Command::Quit,
+                false => return match post_save {
+                    PostSaveAction::Load => Command::Load(state.register),
+                    PostSaveAction::Quit => Command::Quit,
+                    PostSaveAction::Continue => {
+                        Command::None(Model { state, mode: Mode::Normal })
+                    }
                 }
             }
         }
@@ -273,8 +684,143 @@ fn update_save(
     Command::None(Model { state, mode })
 }
 
-/// Update the Model based on `message` and return an IO Command.
-pub fn update(message: Message, state: SessionState) -> Command {
+// Update the Model based on a Help mode message.
+fn update_help(
+    msg: HelpMsg,
+    help_state: HelpState,
+    state: SessionState,
+) -> Command {
+    let mode = match msg {
+        HelpMsg::Open => Mode::Help(help_state),
+        HelpMsg::Up => Mode::Help(help_state.decrement()),
+        HelpMsg::Down => Mode::Help(help_state.increment()),
+        HelpMsg::Close => *help_state.mode,
+    };
+    Command::None(Model { state, mode })
+}
+
+// Return the `index` moved by a scroll wheel step, given the list `size`.
+fn scroll_index(index: usize, down: bool, size: usize) -> usize {
+    match down {
+        true => min(index + SCROLL_STEP, size.saturating_sub(1)),
+        false => index.saturating_sub(SCROLL_STEP),
+    }
+}
+
+// Return the `mode` with its current index moved by a scroll wheel step.
+fn scroll(mode: Mode, down: bool, state: &mut SessionState) -> Mode {
+    let size = state.root.size();
+    match mode {
+        Mode::Load(load_state) => Mode::Load(match down {
+            true => load_state.increment(),
+            false => load_state.decrement(),
+        }),
+        Mode::Normal => {
+            state.cursor = scroll_index(state.cursor, down, size);
+            Mode::Normal
+        }
+        Mode::Select(index) => Mode::Select(scroll_index(index, down, size)),
+        Mode::Target(target_state) => {
+            let target = scroll_index(target_state.target, down, size);
+            TargetState { target, ..target_state }.into_mode()
+        }
+        Mode::Help(help_state) => {
+            let index = scroll_index(help_state.index, down, help_state.rows);
+            Mode::Help(HelpState { index, ..help_state })
+        }
+        mode => mode,
+    }
+}
+
+// Update the Model based on a mouse message.
+//
+// A press selects the item under the pointer, or edits it on a double-click.
+// Releasing on a different item moves the pressed subtree into it.
+fn update_mouse(
+    msg: MouseMsg,
+    mode: Mode,
+    mut state: SessionState,
+) -> Command {
+    let last_press = state.last_press.take();
+    let mode = match (msg, mode) {
+        (
+            MouseMsg::Press(press),
+            Mode::Normal | Mode::Select(_) | Mode::Selected(_),
+        ) => {
+            let double = last_press.filter(|last| {
+                last.row == press.row
+                    && last.index < state.root.size()
+                    && press.time.duration_since(last.time) <= DOUBLE_CLICK
+            });
+            match double {
+                Some(MousePress { index, .. }) => {
+                    let label = state.root.find_label(index);
+                    Mode::Input(InputState::new_edit(label, index))
+                }
+                None => {
+                    let index = press.index;
+                    state.last_press = Some(press);
+                    Mode::Select(index)
+                }
+            }
+        }
+        (MouseMsg::Press(press), Mode::Target(target_state)) => {
+            TargetState { target: press.index, ..target_state }.into_mode()
+        }
+        (MouseMsg::Drag, mode) => {
+            state.last_press = last_press
+                .map(|press| MousePress { dragged: true, ..press });
+            mode
+        }
+        (MouseMsg::Release(target), Mode::Select(source)) => {
+            let target_state =
+                TargetState { target, ..TargetState::new(source) };
+            match last_press {
+                Some(press)
+                    if press.dragged
+                        && press.index == source
+                        && target != source =>
+                {
+                    match target_state.is_valid(&state.root) {
+                        true => {
+                            let (state, index) = state.move_to(
+                                source,
+                                target,
+                                Placement::LastChild,
+                            );
+                            let mode = Mode::Select(index);
+                            return Command::None(Model { state, mode });
+                        }
+                        false => Mode::Select(source),
+                    }
+                }
+                press => {
+                    state.last_press = press;
+                    Mode::Select(source)
+                }
+            }
+        }
+        (MouseMsg::ScrollUp, mode) => scroll(mode, false, &mut state),
+        (MouseMsg::ScrollDown, mode) => scroll(mode, true, &mut state),
+        (_, mode) => mode,
+    };
+    Command::None(Model { state, mode })
+}
+
+// Discard the macro being recorded, showing on the command line that the
+// `input` cannot be recorded.
+fn stop_recording(input: &str, mut state: SessionState) -> Command {
+    state.recording = None;
+    let info = format!("Stopped recording: {input} cannot be recorded");
+    let command_line = CommandLineState {
+        input: LineEditor::default(),
+        info: Some(info),
+    };
+    Command::None(Model { state, mode: command_line.into_mode() })
+}
+
+// Dispatch the `message` to the update function of its mode.
+fn update_mode(message: Message, state: SessionState) -> Command {
     match message {
         Message::Confirm(confirm, confirm_state) => {
             update_confirm(confirm, confirm_state, state)
@@ -288,12 +834,316 @@ pub fn update(message: Message, state: SessionState) -> Command {
             InputState::Filename(filename_state) => {
                 update_filename(msg, filename_state, state)
             }
+            InputState::CommandLine(command_line) => {
+                update_command_line(msg, command_line, state)
+            }
         }
         Message::Select(msg, index) => update_select(msg, index, state),
         Message::Selected(msg, index) => update_selected(msg, index, state),
         Message::Move(msg, index) => update_move(msg, index, state),
+        Message::Target(msg, target_state) => {
+            update_target(msg, target_state, state)
+        }
+        Message::Wrap(msg, wrap_state) => update_wrap(msg, wrap_state, state),
+        Message::Macro(msg, macro_state) => {
+            update_macro(msg, macro_state, state)
+        }
         Message::Save(msg, save_state) => update_save(msg, save_state, state),
+        Message::Help(msg, help_state) => update_help(msg, help_state, state),
+        Message::PasteLines(text) => {
+            let labels = util::parse_outline(&text);
+            let mode = match labels.is_empty() {
+                true => Mode::Normal,
+                false => Mode::Confirm(ConfirmState::CreateItems(labels)),
+            };
+            Command::None(Model { state, mode })
+        }
+        Message::Mouse(msg, mode) => update_mouse(msg, mode, state),
+        Message::Unrecordable(input) => stop_recording(&input, state),
+        Message::Record(..) | Message::Replay(_) => update(message, state),
         Message::Continue(mode) => Command::None(Model { state, mode }),
     }
 }
 
+// Return whether the `message` replayed from a macro has an effect on the
+// session `state`, so that the macro stops at the first action that does not
+// apply, e.g. demoting a first child.
+fn is_applicable(message: &Message, state: &SessionState) -> bool {
+    let root = &state.root;
+    let moves = |index: usize, motion| root.jump(index, motion) != index;
+    match message {
+        Message::Normal(NormalMsg::Select) => root.size() > 0,
+        Message::Normal(NormalMsg::Paste)
+        | Message::Selected(SelectedMsg::Paste(_), _) => {
+            state.register.size() > 0
+        }
+        Message::Normal(NormalMsg::Unhoist)
+        | Message::Select(SelectMsg::Unhoist, _) => state.is_hoisted(),
+        Message::Select(SelectMsg::Decrement, index) => *index > 0,
+        Message::Select(SelectMsg::Increment, index) => {
+            index + 1 < root.size()
+        }
+        Message::Select(SelectMsg::Jump(motion), index) => {
+            moves(*index, *motion)
+        }
+        Message::Select(SelectMsg::Bulk(_), _) => root.mark_count() > 0,
+        Message::Select(SelectMsg::Repeat, _) => state.last_action.is_some(),
+        Message::Move(MoveMsg::Forward, index) => {
+            moves(*index, Motion::NextSibling)
+        }
+        Message::Move(MoveMsg::Backward | MoveMsg::Demote, index) => {
+            moves(*index, Motion::PrevSibling)
+        }
+        Message::Move(MoveMsg::Promote, index) => {
+            moves(*index, Motion::Parent) || moves(*index, Motion::PrevSibling)
+        }
+        Message::Target(TargetMsg::Place(_), target_state) => {
+            target_state.is_valid(root)
+        }
+        _ => true,
+    }
+}
+
+/// Update the Model based on `message` and return an IO Command.
+///
+/// A count typed in Select or Move mode is dropped by any other message.
+/// A key pressed while recording a macro is added to it, unless it stops the
+/// recording, and a macro being played stops at an action that does not
+/// apply, or after too many keys, as a macro may play itself.
+pub fn update(message: Message, mut state: SessionState) -> Command {
+    let message = match message {
+        Message::Record(key, message) => {
+            state.replayed = 0;
+            let is_stop =
+                matches!(*message, Message::Normal(NormalMsg::Record));
+            if let Some(recording) = &mut state.recording {
+                if !is_stop {
+                    recording.keys.push(key);
+                }
+            }
+            *message
+        }
+        Message::Replay(message) => {
+            state = state.pop_replayed();
+            if !is_applicable(&message, &state) {
+                state.replay.clear();
+            }
+            *message
+        }
+        message => {
+            state.replayed = 0;
+            message
+        }
+    };
+    if !matches!(message, Message::Select(..) | Message::Move(..)) {
+        state.count = None;
+    }
+    // Other input may change the rows, so a press before it cannot start a
+    // double-click or drag.
+    if !matches!(message, Message::Mouse(..) | Message::Continue(_)) {
+        state.last_press = None;
+    }
+    match update_mode(message, state) {
+        Command::None(model) => Command::None(model.sync_cursor()),
+        command => command,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use crate::{
+        forest::{Item, Node},
+        keymap::KeyBinding,
+    };
+
+    use super::*;
+
+    // Return the Model updated by the `message`, expecting no IO Command.
+    fn next(message: Message, state: SessionState) -> Model {
+        match update(message, state) {
+            Command::None(model) => model,
+            _ => panic!("Unexpected IO command"),
+        }
+    }
+
+    #[test]
+    fn count_and_repeat_actions() {
+        let state = SessionState::with_labels(&["a", "b", "c", "d", "e"]);
+
+        let model = next(Message::Select(SelectMsg::Count, 0), state);
+        let msg = SelectMsg::Append('3');
+        let model = next(Message::Select(msg, 0), model.state);
+        assert_eq!(model.state.count, Some(3));
+        let model = next(Message::Select(SelectMsg::Increment, 0), model.state);
+        assert!(matches!(model.mode, Mode::Select(3)));
+        assert_eq!(model.state.count, None);
+
+        let model = next(Message::Move(MoveMsg::Count, 3), model.state);
+        let model = next(Message::Move(MoveMsg::Append('2'), 3), model.state);
+        let model = next(Message::Move(MoveMsg::Backward, 3), model.state);
+        assert!(matches!(model.mode, Mode::Move(1)));
+        assert_eq!(model.state.root.find_label(1), "d");
+
+        let model = next(Message::Move(MoveMsg::Count, 1), model.state);
+        let model = next(Message::Normal(NormalMsg::Select), model.state);
+        assert_eq!(model.state.count, None);
+
+        let msg = SelectedMsg::Duplicate;
+        let model = next(Message::Selected(msg, 0), model.state);
+        let model = next(Message::Select(SelectMsg::Repeat, 3), model.state);
+        assert_eq!(model.state.root.size(), 7);
+        assert_eq!(model.state.root.find_label(4), "b");
+    }
+
+    #[test]
+    fn rewrite_affix_with_whitespace() {
+        let mut model = Model {
+            state: SessionState::with_labels(&["WIP a"]),
+            mode: Mode::Input(InputState::new_duplicate_find(0)),
+        };
+        for msg in "WIP ".chars()
+            .map(|c| InputMsg::Edit(InputEdit::Insert(c)))
+            .chain([InputMsg::Submit, InputMsg::Submit])
+        {
+            let Mode::Input(input_state) = model.mode else {
+                panic!("Unexpected mode");
+            };
+            model = next(Message::Input(msg, input_state), model.state);
+        }
+        assert_eq!(model.state.root.size(), 2);
+        assert_eq!(model.state.root.find_label(1), "a");
+    }
+
+    #[test]
+    fn tag_marked_items_with_a_name() {
+        let mut state = SessionState::with_labels(&["a", "b"]);
+        state.root = state.root.toggle_mark(1);
+        let mut model = Model {
+            state,
+            mode: Mode::Input(InputState::new_tag(1)),
+        };
+        for text in [" # ", "x"] {
+            let msgs = text.chars()
+                .map(|c| InputMsg::Edit(InputEdit::Insert(c)))
+                .chain([InputMsg::Submit]);
+            for msg in msgs {
+                let Mode::Input(input_state) = model.mode else {
+                    panic!("Unexpected mode");
+                };
+                model = next(Message::Input(msg, input_state), model.state);
+            }
+        }
+        assert_eq!(model.state.root.outline(), vec![(0, "a"), (0, "b #x")]);
+    }
+
+    #[test]
+    fn paste_cut_items_with_their_ids_once() {
+        let state = SessionState::with_labels(&["a", "b"]);
+        let id = state.root.find_id(0);
+        let state = state.cut(0);
+        let (state, index) = state.paste(0, Placement::After);
+        let (state, _) = state.paste(index, Placement::After);
+        assert_eq!(state.root.find_id(1), id);
+        assert_ne!(state.root.find_id(2), id);
+
+        let mut state = SessionState::with_labels(&["c", "d"]);
+        state.register = Node::Empty.prepend(Item::new(id, "a".to_string()));
+        let (state, _) = state.paste(0, Placement::After);
+        assert_ne!(state.root.find_id(1), id);
+    }
+
+    #[test]
+    fn click_drag_and_double_click() {
+        let press = |index, row| {
+            MouseMsg::Press(MousePress {
+                index,
+                row,
+                offset: 0,
+                time: Instant::now(),
+                dragged: false,
+            })
+        };
+        let mouse = |msg, model: Model| {
+            next(Message::Mouse(msg, model.mode), model.state)
+        };
+        let model = Model {
+            state: SessionState::with_labels(&["a", "b", "c"]),
+            mode: Mode::Normal,
+        };
+
+        let model = mouse(press(0, 2), model);
+        let model = mouse(MouseMsg::Release(2), model);
+        assert!(matches!(model.mode, Mode::Select(0)));
+        assert_eq!(model.state.root.find_label(0), "a");
+        let model = mouse(press(1, 2), model);
+        assert!(matches!(model.mode, Mode::Input(InputState::Label(
+            LabelState { action: LabelAction::Edit(0), .. }
+        ))));
+
+        let model = Model { mode: Mode::Normal, ..model };
+        let model = mouse(press(0, 3), model);
+        let model = mouse(MouseMsg::Drag, model);
+        let model = mouse(MouseMsg::Release(2), model);
+        assert_eq!(model.state.root.outline(), vec![
+            (0, "b"),
+            (0, "c"),
+            (1, "a"),
+        ]);
+
+        let model = mouse(press(2, 4), Model { mode: Mode::Normal, ..model });
+        let model = next(Message::Selected(SelectedMsg::Cut, 2), model.state);
+        let model = mouse(press(1, 4), model);
+        assert!(matches!(model.mode, Mode::Select(1)));
+    }
+
+    #[test]
+    fn stop_recording_unrecordable_input() {
+        let mut state = SessionState::with_labels(&["a"]);
+        let keys = vec![KeyBinding::parse("j").unwrap()];
+        state.recording = Some(Recording { register: 'a', keys });
+        let message = Message::Unrecordable("pastes".to_string());
+        let model = next(message, state);
+
+        assert!(model.state.recording.is_none());
+        assert!(matches!(
+            model.mode,
+            Mode::Input(InputState::CommandLine(CommandLineState {
+                info: Some(info),
+                ..
+            })) if info == "Stopped recording: pastes cannot be recorded",
+        ));
+    }
+
+    #[test]
+    fn stop_macro_playing_itself() {
+        let key = KeyBinding::parse("j").unwrap();
+        let mut state = SessionState::with_labels(&["a"]).play(&[key, key], 1);
+        for _ in 0..20_000 {
+            state = state.play(&[key], 1);
+            let message = Message::Continue(Mode::Normal);
+            state = next(Message::Replay(Box::new(message)), state).state;
+        }
+        assert!(state.replay.is_empty());
+    }
+
+    #[test]
+    fn extend_wrap_to_last_sibling() {
+        let mut model = Model {
+            state: SessionState::with_labels(&["a", "b", "c"]),
+            mode: WrapState::new(1).into_mode(),
+        };
+        for _ in 0..3 {
+            let Mode::Wrap(wrap_state) = model.mode else {
+                panic!("Expected wrap mode");
+            };
+            let message = Message::Wrap(WrapMsg::Extend, wrap_state);
+            model = next(message, model.state);
+        }
+        assert!(matches!(
+            model.mode,
+            Mode::Wrap(WrapState { index: 1, count: 2 }),
+        ));
+    }
+}
@@ -1,3 +1,12 @@
+// Number of spaces a tab counts as when measuring indentation.
+const TAB_WIDTH: usize = 4;
+
+// Largest count that can be typed to repeat an action.
+const MAX_COUNT: usize = 999;
+
+/// Separator between the labels of a path from a root to a node.
+pub const PATH_SEPARATOR: &str = " › ";
+
 /// Append a digit to `index` if valid, otherwise return a fallback value.
 pub fn append_index(index: usize, c: char, list_size: usize) -> usize {
     if !c.is_ascii_digit() {
@@ -16,6 +25,14 @@ pub fn append_index(index: usize, c: char, list_size: usize) -> usize {
     index
 }
 
+/// Append a digit to the `count` if it stays within the maximum count.
+pub fn append_count(count: usize, c: char) -> usize {
+    match c.to_digit(10).map(|digit| count * 10 + digit as usize) {
+        Some(new_count) if new_count <= MAX_COUNT => new_count,
+        _ => count,
+    }
+}
+
 /// Return the maximum character length of a 0-indexed list's indices.
 pub fn max_index_length(list_size: usize) -> usize {
     match list_size {
@@ -24,3 +41,59 @@ pub fn max_index_length(list_size: usize) -> usize {
     }
 }
 
+/// Split the `text` into its non-blank trimmed lines, each paired with a depth
+/// based on its indentation relative to the lines before it.
+pub fn parse_outline(text: &str) -> Vec<(usize, String)> {
+    let mut indents: Vec<usize> = Vec::new();
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let label = line.trim_start();
+            let indent = line[..line.len() - label.len()]
+                .chars()
+                .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+                .sum();
+            while indents.last().is_some_and(|&last| last >= indent) {
+                indents.pop();
+            }
+            let depth = indents.len();
+            indents.push(indent);
+            (depth, label.trim_end().to_string())
+        })
+        .collect()
+}
+
+/// Replace `from` with `to` at the start of `label`, or else at its end.
+pub fn rewrite_affix(label: String, from: &str, to: &str) -> String {
+    if let Some(rest) = label.strip_prefix(from) {
+        format!("{to}{rest}")
+    } else if let Some(rest) = label.strip_suffix(from) {
+        format!("{rest}{to}")
+    } else {
+        label
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_outline_indentation() {
+        let text = "a\n\tb\n    c\n  \t d\n  e\n\n f\ng  \n";
+        let outline = parse_outline(text);
+        let outline: Vec<_> = outline.iter()
+            .map(|(depth, label)| (*depth, label.as_str()))
+            .collect();
+
+        assert_eq!(outline, vec![
+            (0, "a"),
+            (1, "b"),
+            (1, "c"),
+            (2, "d"),
+            (1, "e"),
+            (1, "f"),
+            (0, "g"),
+        ]);
+    }
+}
@@ -1,58 +1,89 @@
 mod cmdbar;
 mod forest_view;
 mod statusbar;
-mod style;
 
-use std::cmp::min;
+use std::{
+    cmp::{max, min},
+    ops::Range,
+};
 
+use crossterm::event::KeyEvent;
 use ratatui::{
     layout::{Constraint, Layout},
-    prelude::{Buffer, Rect, Widget},
-    style::Styled,
+    prelude::{Buffer, Position, Rect, Widget},
+    style::{Styled, Stylize},
     text::{Line, Text},
     widgets::{
         block::Padding,
         Block,
         Borders,
         Paragraph,
-        Wrap,
     },
     Frame,
 };
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 use crate::{
+    config::Config,
+    editor::LineEditor,
+    forest::Node,
     io::LoadState,
+    keymap::{HelpRow, Keymap},
     model::{
         ConfirmState,
+        HelpState,
         Mode,
         Model,
         SessionState,
+        WrapState,
     },
+    theme::Theme,
     util,
 };
 
 use self::{
-    cmdbar::command_bar,
+    cmdbar::{command_bar, command_key},
     forest_view::{
         forest_normal,
+        forest_range,
         forest_select,
         forest_selected,
+        forest_target,
     },
     statusbar::status_bar,
 };
 
+const PROMPT: &str = "❯ ";
+
 // Scroll offset and flags for scrolling indicators.
 struct ScrollInfo {
-    offset: u16,
+    offset: usize,
     is_more_above: bool,
     is_more_below: bool,
 }
 
-// A widget containing scrolling text.
+// Builds the text of the rows in an index range of a scrolling list.
+type Rows<'a> = Box<dyn FnOnce(Range<usize>) -> Text<'a> + 'a>;
+
+// A widget scrolling a list of `list_size` rows to show the row at `index`,
+// styled by the `theme`. Only the `rows` shown are built.
 struct Scroll<'a> {
-    text: Text<'a>,
+    rows: Rows<'a>,
     list_size: usize,
     index: usize,
+    theme: &'a Theme,
+}
+
+impl<'a> Scroll<'a> {
+    fn new(
+        rows: impl FnOnce(Range<usize>) -> Text<'a> + 'a,
+        list_size: usize,
+        index: usize,
+        theme: &'a Theme,
+    ) -> Self {
+        Scroll { rows: Box::new(rows), list_size, index, theme }
+    }
 }
 
 // Calculate the scroll offset and other scroll info.
@@ -66,7 +97,7 @@ fn compute_scroll_info(
     let max_offset = list_size.saturating_sub(area_height);
     let offset = min(centered, max_offset);
     ScrollInfo {
-        offset: offset as u16,
+        offset,
         is_more_above: offset > 0,
         is_more_below: offset < max_offset,
     }
@@ -85,136 +116,394 @@ fn top_mid_bottom(area: Rect) -> [Rect; 3] {
 impl<'a> Widget for Scroll<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let [top_line, mid_area, bottom_line] = top_mid_bottom(area);
-        let Scroll { text, list_size, index } = self;
+        let Scroll { rows, list_size, index, theme } = self;
         let ScrollInfo { offset, is_more_above, is_more_below } =
             compute_scroll_info(mid_area.height, list_size, index);
-        main_paragraph_scroll(text)
-            .scroll((offset, 0))
+        let end = min(offset + mid_area.height as usize, list_size);
+        main_paragraph_scroll(rows(offset..end), theme)
             .render(mid_area, buf);
         let scroll_hint = |is_more: bool| if is_more { " ..." } else { "" };
         Text::from(scroll_hint(is_more_above))
-            .style(style::DEFAULT)
+            .style(theme.default)
             .render(top_line, buf);
         Text::from(scroll_hint(is_more_below))
-            .style(style::DEFAULT)
+            .style(theme.default)
             .render(bottom_line, buf);
     }
 }
 
 // Create a paragraph with the `text` and `padding`.
-fn pad_main_paragraph(text: Text, padding: Padding) -> Paragraph {
+fn pad_main_paragraph<'a>(
+    text: Text<'a>,
+    padding: Padding,
+    theme: &Theme,
+) -> Paragraph<'a> {
     let block = Block::new()
         .borders(Borders::NONE)
         .padding(padding);
     Paragraph::new(text)
         .block(block)
         .left_aligned()
-        .set_style(style::DEFAULT)
+        .set_style(theme.default)
 }
 
 // Style the `text` to display in the main area.
-fn main_paragraph(text: Text) -> Paragraph {
-    pad_main_paragraph(text, Padding::uniform(1))
+fn main_paragraph<'a>(text: Text<'a>, theme: &Theme) -> Paragraph<'a> {
+    pad_main_paragraph(text, Padding::uniform(1), theme)
 }
 
 // Style the `text` to display in the main area for scrolling.
-fn main_paragraph_scroll(text: Text) -> Paragraph {
-    pad_main_paragraph(text, Padding::horizontal(1))
+fn main_paragraph_scroll<'a>(text: Text<'a>, theme: &Theme) -> Paragraph<'a> {
+    pad_main_paragraph(text, Padding::horizontal(1), theme)
+}
+
+// Return the text stating the number of descendants deleted with an item.
+fn descendant_count(count: usize) -> String {
+    match count {
+        1 => "and 1 descendant".to_string(),
+        n => format!("and {n} descendants"),
+    }
+}
+
+// Return the text stating the number of marked items to delete, and their
+// descendants.
+fn marked_count(root: &Node) -> Text<'static> {
+    let ranges = root.marked_trees();
+    let items = ranges.len();
+    let total: usize = ranges.iter()
+        .map(|range| range.len())
+        .sum();
+    let items_text = match items {
+        1 => "1 marked item".to_string(),
+        n => format!("{n} marked items"),
+    };
+    Text::from(vec![
+        Line::from(items_text),
+        Line::default(),
+        Line::from(descendant_count(total - items).bold()),
+    ])
+}
+
+// Return the text showing the `labels` indented by their depths.
+fn outline(labels: &[(usize, String)]) -> Text<'_> {
+    let lines = labels.iter()
+        .map(|(depth, label)| {
+            Line::from(format!("{}{label}", "   ".repeat(*depth)))
+        });
+    Text::from_iter(lines)
 }
 
 // Return the confirm widget.
-fn confirm(confirm_state: &ConfirmState) -> Paragraph {
+fn confirm<'a>(
+    confirm_state: &'a ConfirmState,
+    root: &Node,
+    theme: &Theme,
+) -> Paragraph<'a> {
     let text = match confirm_state {
         ConfirmState::NewSession => Text::default(),
         ConfirmState::DeleteItem(label, _) => Text::from(label.as_str()),
+        ConfirmState::DeleteTree(label, index) => {
+            let count = root.tree_size(*index) - 1;
+            Text::from(vec![
+                Line::from(label.as_str()),
+                Line::default(),
+                Line::from(descendant_count(count).bold()),
+            ])
+        }
+        ConfirmState::DeleteMarked => marked_count(root),
         ConfirmState::DeleteFile(load_state) => Text::from(load_state.filename()),
+        ConfirmState::CreateItems(labels) => outline(labels),
     };
-    main_paragraph(text)
+    main_paragraph(text, theme)
 }
 
 // Return the load widget.
-fn load(load_state: &LoadState) -> Scroll {
+fn load<'a>(load_state: &'a LoadState, theme: &'a Theme) -> Scroll<'a> {
     let selected = load_state.index();
     let index_len = util::max_index_length(load_state.size());
-    let lines = load_state.filename_iter()
-        .enumerate()
-        .map(|(i, filename)| {
-            let highlight = i == selected;
-            let line_style = match highlight {
-                true => style::DEFAULT_HL,
-                false => style::DEFAULT,
-            };
-            let text = format!(" {i:>width$}   {filename} ", width = index_len);
-            Line::styled(text, line_style)
-        });
-    Scroll {
-        text: Text::from_iter(lines),
-        list_size: load_state.size(),
-        index: load_state.index(),
+    let rows = move |range: Range<usize>| {
+        let lines = load_state.filename_iter()
+            .enumerate()
+            .skip(range.start)
+            .take(range.len())
+            .map(|(i, filename)| {
+                let highlight = i == selected;
+                let line_style = match highlight {
+                    true => theme.default_hl,
+                    false => theme.default,
+                };
+                let text =
+                    format!(" {i:>width$}   {filename} ", width = index_len);
+                Line::styled(text, line_style)
+            });
+        Text::from_iter(lines)
+    };
+    Scroll::new(rows, load_state.size(), selected, theme)
+}
+
+// Return the `text` padded with spaces to the display `width`.
+fn pad(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(text.width());
+    format!("{text}{}", " ".repeat(padding))
+}
+
+// Return the help widget listing the key bindings of the mode under the help.
+fn help<'a>(
+    help_state: &HelpState,
+    keymap: &Keymap,
+    theme: &'a Theme,
+) -> Scroll<'a> {
+    let rows = keymap.help(&help_state.mode);
+    let column_width = |width: fn(&HelpRow) -> usize| {
+        rows.iter().map(width).max().unwrap_or(0)
+    };
+    let keys_width = column_width(|row| row.keys.width());
+    let name_width = column_width(|row| row.name.width());
+    let size = rows.len();
+    let selected = help_state.index;
+    let lines = move |range: Range<usize>| {
+        let lines = rows.into_iter()
+            .enumerate()
+            .skip(range.start)
+            .take(range.len())
+            .map(|(i, HelpRow { keys, name, description })| {
+                let keys = pad(&keys, keys_width);
+                let name = pad(name, name_width);
+                let text = format!(" {keys}   {name}   {description} ");
+                match i == selected {
+                    true => Line::styled(text, theme.default_hl),
+                    false => Line::styled(text, theme.default),
+                }
+            });
+        Text::from_iter(lines)
+    };
+    Scroll::new(lines, size, selected, theme)
+}
+
+// Return the index of the item the forest view is scrolled to in the `mode`,
+// if it shows the forest.
+fn forest_focus(state: &SessionState, mode: &Mode) -> Option<usize> {
+    match mode {
+        Mode::Normal => Some(state.cursor),
+        Mode::Select(index)
+        | Mode::Selected(index)
+        | Mode::Move(index)
+        | Mode::Wrap(WrapState { index, .. }) => Some(*index),
+        Mode::Target(target_state) => Some(target_state.target),
+        _ => None,
     }
 }
 
-// Return the text input widget given the `input` string.
-fn text_input(input: &str) -> Paragraph {
-    let content = format!("❯ {input}").into();
-    let cursor = "█".set_style(style::CURSOR);
-    let text = Line::from(vec![content, cursor])
-        .set_style(style::DEFAULT)
-        .into();
-    main_paragraph(text)
-        .wrap(Wrap { trim: false })
+// Return the forest widget for the `mode`, if it shows the forest.
+fn forest<'a>(
+    state: &'a SessionState,
+    mode: &Mode,
+    theme: &'a Theme,
+) -> Option<Scroll<'a>> {
+    let SessionState { root, cursor, .. } = state;
+    let widget = match mode {
+        Mode::Normal => forest_normal(root, *cursor, theme),
+        Mode::Select(index) => forest_select(root, *index, theme),
+        Mode::Selected(index) | Mode::Move(index) => {
+            forest_selected(root, *index, theme)
+        }
+        Mode::Target(target_state) => {
+            let sources = target_state.source_ranges(root);
+            forest_target(root, sources, target_state.target, theme)
+        }
+        Mode::Wrap(WrapState { index, count }) => {
+            let end = index + root.siblings_size(*index, *count);
+            forest_range(root, *index..end, theme)
+        }
+        _ => return None,
+    };
+    Some(widget)
+}
+
+// Return the area of the forest view within the terminal `area`.
+fn forest_area(area: Rect) -> Rect {
+    let [_, main_area, _] = top_mid_bottom(area);
+    let [_, mid_area, _] = top_mid_bottom(main_area);
+    mid_area
+}
+
+/// Return the scroll offset of the forest view in the `mode`, given the
+/// terminal `area`, if it shows the forest.
+pub fn forest_offset(
+    state: &SessionState,
+    mode: &Mode,
+    area: Rect,
+) -> Option<usize> {
+    let index = forest_focus(state, mode)?;
+    let height = forest_area(area).height;
+    Some(compute_scroll_info(height, state.root.size(), index).offset)
+}
+
+/// Return the index of the item shown on screen `row` when the forest view
+/// is scrolled by `offset`, given the terminal `area`.
+pub fn forest_index_at(
+    state: &SessionState,
+    area: Rect,
+    offset: usize,
+    row: u16,
+) -> Option<usize> {
+    let mid_area = forest_area(area);
+    if !(mid_area.top()..mid_area.bottom()).contains(&row) {
+        return None;
+    }
+    let i = (row - mid_area.y) as usize + offset;
+    (i < state.root.size()).then_some(i)
+}
+
+/// Return the key of the command shown at screen `column` and `row` on the
+/// command bar in the `mode`, given the `keymap` and terminal `area`.
+pub fn command_key_at(
+    state: &SessionState,
+    mode: &Mode,
+    keymap: &Keymap,
+    area: Rect,
+    column: u16,
+    row: u16,
+) -> Option<KeyEvent> {
+    let [_, _, command_bar_area] = top_mid_bottom(area);
+    match row == command_bar_area.y {
+        true => {
+            command_key(state, mode, keymap, command_bar_area.width, column)
+        }
+        false => None,
+    }
+}
+
+// Return the text input widget for the `input` line, wrapped to fit in the
+// `area`, and the screen position of its cursor.
+fn text_input(
+    input: &LineEditor,
+    area: Rect,
+    theme: &Theme,
+) -> (Paragraph<'static>, Position) {
+    let width = max(area.width.saturating_sub(2), 1) as usize;
+    let mut rows = vec![PROMPT.to_string()];
+    let mut row_width = PROMPT.width();
+    let mut cursor = None;
+    for (i, grapheme) in input.text().grapheme_indices(true) {
+        let grapheme_width = grapheme.width();
+        if row_width + grapheme_width > width {
+            rows.push(String::new());
+            row_width = 0;
+        }
+        if i == input.cursor() {
+            cursor = Some((row_width, rows.len() - 1));
+        }
+        rows.last_mut()
+            .expect("Rows are never empty")
+            .push_str(grapheme);
+        row_width += grapheme_width;
+    }
+    let (x, y) = match cursor {
+        Some(position) => position,
+        None if row_width >= width => (0, rows.len()),
+        None => (row_width, rows.len() - 1),
+    };
+    let position = Position::new(area.x + 1 + x as u16, area.y + 1 + y as u16);
+    let lines = rows.into_iter().map(Line::from);
+    (main_paragraph(Text::from_iter(lines), theme), position)
 }
 
 // Return the save query widget.
-fn save_query(save: bool) -> Paragraph<'static> {
+fn save_query(save: bool, theme: &Theme) -> Paragraph<'static> {
     let line1 = Line::from(" Save ");
     let line2 = Line::from(" Discard Changes ");
     let lines = match save {
         true => vec![
-            line1.set_style(style::DEFAULT_HL),
+            line1.set_style(theme.default_hl),
             line2,
         ],
         false => vec![
             line1,
-            line2.set_style(style::DEFAULT_HL),
+            line2.set_style(theme.default_hl),
         ],
     };
-    main_paragraph(Text::from(lines))
+    main_paragraph(Text::from(lines), theme)
 }
 
-/// Render the UI on the `frame` based on the current `model`.
-pub fn view(model: &Model, frame: &mut Frame) {
+/// Render the UI on the `frame` based on the current `model` and the user
+/// `config`.
+pub fn view(model: &Model, config: &Config, frame: &mut Frame) {
     let [
         status_bar_area,
         main_area,
         command_bar_area
     ] = top_mid_bottom(frame.area());
-    frame.render_widget(status_bar(model), status_bar_area);
+    let theme = &config.theme;
+    let status_bar = status_bar(model, status_bar_area.width, theme);
+    frame.render_widget(status_bar, status_bar_area);
     let Model { state, mode } = model;
-    let SessionState { root, .. } = state;
+    if let Some(widget) = forest(state, mode, theme) {
+        frame.render_widget(widget, main_area);
+    }
+    let root = &state.root;
     match mode {
         Mode::Confirm(confirm_state) => {
-            frame.render_widget(confirm(confirm_state), main_area);
+            let widget = confirm(confirm_state, root, theme);
+            frame.render_widget(widget, main_area);
         }
         Mode::Load(load_state) => {
-            frame.render_widget(load(load_state), main_area);
-        }
-        Mode::Normal => {
-            frame.render_widget(forest_normal(root), main_area);
+            frame.render_widget(load(load_state, theme), main_area);
         }
         Mode::Input(input_state) => {
-            frame.render_widget(text_input(input_state.input()), main_area);
-        }
-        Mode::Select(index) => {
-            frame.render_widget(forest_select(root, *index), main_area);
-        }
-        Mode::Selected(index) | Mode::Move(index) => {
-            frame.render_widget(forest_selected(root, *index), main_area);
+            let (widget, cursor) =
+                text_input(input_state.input(), main_area, theme);
+            frame.render_widget(widget, main_area);
+            frame.set_cursor_position(cursor);
         }
+        Mode::Normal
+        | Mode::Select(_)
+        | Mode::Selected(_)
+        | Mode::Move(_)
+        | Mode::Target(_)
+        | Mode::Wrap(_)
+        | Mode::Macro(_) => (),
         Mode::Save(save_state) => {
-            frame.render_widget(save_query(save_state.save), main_area);
+            let widget = save_query(save_state.save, theme);
+            frame.render_widget(widget, main_area);
+        }
+        Mode::Help(help_state) => {
+            let widget = help(help_state, &config.keymap, theme);
+            frame.render_widget(widget, main_area);
         }
     }
-    frame.render_widget(command_bar(model), command_bar_area);
+    let command_bar = command_bar(model, &config.keymap, theme);
+    frame.render_widget(command_bar, command_bar_area);
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_rows_to_items() {
+        let labels: Vec<usize> = (0..40).collect();
+        let state = SessionState::with_labels(&labels);
+        let area = Rect::new(0, 0, 80, 24);
+
+        assert_eq!(forest_offset(&state, &Mode::Normal, area), Some(0));
+        assert_eq!(forest_offset(&state, &Mode::Select(15), area), Some(5));
+        assert_eq!(forest_offset(&state, &Mode::Select(39), area), Some(20));
+        assert_eq!(forest_index_at(&state, area, 0, 17), Some(15));
+        assert_eq!(forest_index_at(&state, area, 5, 17), Some(20));
+        assert_eq!(forest_index_at(&state, area, 20, 21), Some(39));
+        assert_eq!(forest_index_at(&state, area, 0, 1), None);
+        assert_eq!(forest_index_at(&state, area, 0, 22), None);
+    }
+
+    #[test]
+    fn map_rows_past_u16_max() {
+        let labels: Vec<usize> = (0..70_000).collect();
+        let state = SessionState::with_labels(&labels);
+        let area = Rect::new(0, 0, 80, 24);
+        let offset = forest_offset(&state, &Mode::Select(69_990), area);
+
+        assert_eq!(offset, Some(69_980));
+        assert_eq!(forest_index_at(&state, area, 69_980, 17), Some(69_995));
+    }
+}
@@ -1,116 +1,479 @@
+use crossterm::event::KeyEvent;
 use ratatui::{
     style::Styled,
     text::Line,
 };
+use unicode_width::UnicodeWidthStr;
 
 use crate::{
-    forest::Node,
+    forest::{Motion, Node, Placement},
+    keymap::{Keymap, ModeKeys},
+    message::{
+        BulkMsg,
+        HelpMsg,
+        InputMsg,
+        LoadMsg,
+        MacroMsg,
+        MoveMsg,
+        NormalMsg,
+        SaveMsg,
+        SelectedMsg,
+        SelectMsg,
+        TargetMsg,
+        WrapMsg,
+    },
     model::{
         ConfirmState,
         InputState,
+        MacroState,
         Mode,
         Model,
+        SessionState,
+        TargetState,
     },
-    view::style,
+    theme::Theme,
 };
 
-type KeyPair<'a> = (&'a str, &'a str);
-
-const JUMP: KeyPair = ("0-9", "Jump");
-const DOWN_UP: KeyPair = ("J/K │ ↓/↑", "Down/Up");
-const OPEN: KeyPair = ("Enter", "Open");
-const SUBMIT: KeyPair = ("Enter", "Submit");
-const CONFIRM: KeyPair = ("Enter", "Confirm");
-const DONE: KeyPair = ("Enter", "Done");
-const NEW: KeyPair = ("N", "New");
-const LOAD: KeyPair = ("L", "Load");
-const QUIT: KeyPair = ("Q", "Quit");
-const ADD: KeyPair = ("A", "Add");
-const SELECT: KeyPair = ("S", "Select");
-const EDIT: KeyPair = ("E", "Edit");
-const MOVE: KeyPair = ("M", "Move");
-const DOWN: KeyPair = ("J │ ↓", "Down");
-const UP: KeyPair = ("K │ ↑", "Up");
-const PROMOTE: KeyPair = ("H │ ←", "Promote");
-const DEMOTE: KeyPair = ("L │ →", "Demote");
-const RENAME: KeyPair = ("R", "Rename");
-const DELETE: KeyPair = ("D", "Delete");
-const TOGGLE: KeyPair = ("Space", "Toggle");
-const CANCEL: KeyPair = ("Esc", "Cancel");
+const SPACER: &str = "    ";
+const KEY_SEPARATOR: &str = " │ ";
+
+// A command shown on the command bar: the labels of its `keys`, its `name`,
+// and the key pressed when it is clicked (if it is a single action).
+struct KeyPair {
+    keys: String,
+    name: &'static str,
+    click: Option<KeyEvent>,
+}
+
+// Return the pair for the command `name` sending `msg`, if any keys are bound
+// to it.
+fn pair<T: Clone + PartialEq>(
+    keys: &ModeKeys<T>,
+    msg: T,
+    name: &'static str,
+) -> Option<KeyPair> {
+    let bound = keys.keys(&msg);
+    let labels: Vec<String> = bound.iter()
+        .map(ToString::to_string)
+        .collect();
+    Some(KeyPair {
+        keys: labels.join(KEY_SEPARATOR),
+        name,
+        click: Some(bound.first()?.to_event()),
+    })
+}
+
+// Return the pair for the command `name` made of two opposite actions, with
+// their keys shown side by side, e.g. "J/K │ ↓/↑".
+fn pair2<T: Clone + PartialEq>(
+    keys: &ModeKeys<T>,
+    first: T,
+    second: T,
+    name: &'static str,
+) -> Option<KeyPair> {
+    let labels: Vec<String> = keys.keys(&first).iter()
+        .zip(keys.keys(&second))
+        .map(|(first, second)| format!("{first}/{second}"))
+        .collect();
+    match labels.is_empty() {
+        true => None,
+        false => Some(KeyPair {
+            keys: labels.join(KEY_SEPARATOR),
+            name,
+            click: None,
+        }),
+    }
+}
+
+// Return the pair for jumping to an item by typing its index.
+fn jump() -> Option<KeyPair> {
+    Some(KeyPair { keys: "0-9".to_string(), name: "Jump", click: None })
+}
+
+// Return the pair for a command chosen by typing one of the `keys`.
+fn typed(keys: &str, name: &'static str) -> Option<KeyPair> {
+    Some(KeyPair { keys: keys.to_string(), name, click: None })
+}
 
 // Return the confirm mode key-command pairs.
-fn confirm_mode_commands(confirm_state: &ConfirmState) -> Vec<KeyPair<'static>> {
+fn confirm_mode_commands(
+    confirm_state: &ConfirmState,
+    keys: &ModeKeys<bool>,
+) -> Vec<Option<KeyPair>> {
+    let confirm = pair(keys, true, "Confirm");
     match confirm_state {
-        ConfirmState::NewSession => vec![CONFIRM],
-        _ => vec![CONFIRM, CANCEL],
+        ConfirmState::NewSession => vec![confirm],
+        _ => vec![confirm, pair(keys, false, "Cancel")],
     }
 }
 
 // Return the load mode key-command pairs.
-fn load_mode_commands(file_count: usize) -> Vec<KeyPair<'static>> {
+fn load_mode_commands(
+    file_count: usize,
+    keys: &ModeKeys<LoadMsg>,
+) -> Vec<Option<KeyPair>> {
     let mut pairs = Vec::new();
     if file_count > 1 {
-        pairs.extend(&[JUMP, DOWN_UP]);
+        pairs.push(jump());
+        pairs.push(
+            pair2(keys, LoadMsg::Increment, LoadMsg::Decrement, "Down/Up")
+        );
     }
-    pairs.extend(&[OPEN, NEW, RENAME, DELETE, QUIT]);
+    pairs.extend([
+        pair(keys, LoadMsg::Open, "Open"),
+        pair(keys, LoadMsg::New, "New"),
+        pair(keys, LoadMsg::Rename, "Rename"),
+        pair(keys, LoadMsg::Delete, "Delete"),
+        pair(keys, LoadMsg::Quit, "Quit"),
+    ]);
     pairs
 }
 
 // Return the normal mode key-command pairs.
-fn normal_mode_commands(root: &Node) -> Vec<KeyPair> {
-    let mut pairs = vec![ADD];
-    if root.size() > 0 {
-        pairs.push(SELECT);
+fn normal_mode_commands(
+    state: &SessionState,
+    keys: &ModeKeys<NormalMsg>,
+) -> Vec<Option<KeyPair>> {
+    let mut pairs = vec![pair(keys, NormalMsg::Input, "Add")];
+    if state.root.size() > 0 {
+        pairs.push(pair(keys, NormalMsg::Select, "Select"));
     }
-    pairs.extend(&[LOAD, QUIT]);
+    if state.register.size() > 0 {
+        pairs.push(pair(keys, NormalMsg::Paste, "Paste"));
+    }
+    if state.is_hoisted() {
+        pairs.push(pair(keys, NormalMsg::Unhoist, "Unhoist"));
+    }
+    pairs.push(pair(keys, NormalMsg::Command, "Command"));
+    let record = match state.recording {
+        Some(_) => "Stop Recording",
+        None => "Record",
+    };
+    pairs.push(pair(keys, NormalMsg::Record, record));
+    pairs.push(pair(keys, NormalMsg::Play, "Play"));
+    pairs.push(pair(keys, NormalMsg::Load, "Load"));
+    pairs.push(pair(keys, NormalMsg::Quit, "Quit"));
     pairs
 }
 
 // Return the input mode key-command pairs.
-fn input_mode_commands(input_state: &InputState) -> Vec<KeyPair> {
+fn input_mode_commands(
+    input_state: &InputState,
+    keys: &ModeKeys<InputMsg>,
+) -> Vec<Option<KeyPair>> {
+    let mut pairs = Vec::new();
+    if let InputState::CommandLine(_) = input_state {
+        pairs.push(pair(keys, InputMsg::Complete, "Complete"));
+    }
     if input_state.is_valid() {
-        vec![SUBMIT, CANCEL]
-    } else {
-        vec![CANCEL]
+        pairs.push(pair(keys, InputMsg::Submit, "Submit"));
     }
+    pairs.push(pair(keys, InputMsg::Cancel, "Cancel"));
+    pairs
 }
 
 // Return the select mode key-command pairs.
-fn select_mode_commands(size: usize) -> Vec<KeyPair<'static>> {
+fn select_mode_commands(
+    state: &SessionState,
+    keys: &ModeKeys<SelectMsg>,
+) -> Vec<Option<KeyPair>> {
+    let root = &state.root;
+    let jump_pair = |first, second, name| {
+        pair2(keys, SelectMsg::Jump(first), SelectMsg::Jump(second), name)
+    };
+    let bulk_pair = |msg, name| pair(keys, SelectMsg::Bulk(msg), name);
     let mut pairs = Vec::new();
-    if size > 1 {
-        pairs.extend(&[JUMP, DOWN_UP]);
+    if root.size() > 1 {
+        pairs.extend([
+            jump(),
+            pair2(keys, SelectMsg::Increment, SelectMsg::Decrement, "Down/Up"),
+            jump_pair(Motion::Parent, Motion::FirstChild, "Parent/Child"),
+            jump_pair(
+                Motion::PrevSibling,
+                Motion::NextSibling,
+                "Prev/Next Sibling",
+            ),
+            jump_pair(Motion::FirstRoot, Motion::LastRoot, "First/Last Root"),
+            pair(keys, SelectMsg::Jump(Motion::NextAtDepth), "Next at Depth"),
+            pair2(keys, SelectMsg::MarkDown, SelectMsg::MarkUp, "Mark Range"),
+            pair(keys, SelectMsg::Count, "Count"),
+        ]);
     }
-    pairs.extend(&[CONFIRM, CANCEL]);
+    if state.last_action.is_some() {
+        pairs.push(pair(keys, SelectMsg::Repeat, "Repeat"));
+    }
+    pairs.push(pair(keys, SelectMsg::ToggleMark, "Mark"));
+    if root.mark_count() > 0 {
+        pairs.extend([
+            pair(keys, SelectMsg::ClearMarks, "Unmark All"),
+            bulk_pair(BulkMsg::Cut, "Cut"),
+            bulk_pair(BulkMsg::Move, "Move To"),
+            bulk_pair(BulkMsg::Sort, "Sort Children"),
+            bulk_pair(BulkMsg::Tag, "Tag"),
+            bulk_pair(BulkMsg::Delete, "Delete"),
+        ]);
+    }
+    if state.is_hoisted() {
+        pairs.push(pair(keys, SelectMsg::Unhoist, "Unhoist"));
+    }
+    pairs.push(pair(keys, SelectMsg::Command, "Command"));
+    pairs.push(pair(keys, SelectMsg::Confirm, "Confirm"));
+    pairs.push(pair(keys, SelectMsg::Cancel, "Cancel"));
     pairs
 }
 
-// Convert key-command pairs into a command bar.
-fn to_command_bar(pairs: Vec<KeyPair>) -> Line {
+// Return the selected mode key-command pairs.
+fn selected_mode_commands(
+    register: &Node,
+    keys: &ModeKeys<SelectedMsg>,
+) -> Vec<Option<KeyPair>> {
+    let mut pairs = vec![
+        pair(keys, SelectedMsg::Edit, "Edit"),
+        pair(keys, SelectedMsg::Move, "Move"),
+        pair(keys, SelectedMsg::MoveTo, "Move To"),
+        pair(keys, SelectedMsg::Cut, "Cut"),
+        pair(keys, SelectedMsg::Copy, "Copy"),
+        pair(keys, SelectedMsg::CopyPath, "Copy Path"),
+    ];
+    if register.size() > 0 {
+        let paste_pair = |placement, name| {
+            pair(keys, SelectedMsg::Paste(placement), name)
+        };
+        pairs.extend([
+            paste_pair(Placement::After, "Paste After"),
+            paste_pair(Placement::Before, "Paste Before"),
+            paste_pair(Placement::FirstChild, "Paste Child"),
+        ]);
+    }
+    pairs.extend([
+        pair(keys, SelectedMsg::Duplicate, "Duplicate"),
+        pair(keys, SelectedMsg::DuplicateRewrite, "Duplicate & Rewrite"),
+        pair(keys, SelectedMsg::Wrap, "Wrap"),
+        pair(keys, SelectedMsg::Hoist, "Hoist"),
+        pair(keys, SelectedMsg::Sort, "Sort Children"),
+        pair(keys, SelectedMsg::Delete, "Delete"),
+        pair(keys, SelectedMsg::DeleteTree, "Delete Tree"),
+        pair(keys, SelectedMsg::Command, "Command"),
+        pair(keys, SelectedMsg::Cancel, "Cancel"),
+    ]);
+    pairs
+}
+
+// Return the move mode key-command pairs.
+fn move_mode_commands(keys: &ModeKeys<MoveMsg>) -> Vec<Option<KeyPair>> {
+    vec![
+        pair(keys, MoveMsg::Forward, "Down"),
+        pair(keys, MoveMsg::Backward, "Up"),
+        pair(keys, MoveMsg::Promote, "Promote"),
+        pair(keys, MoveMsg::Demote, "Demote"),
+        pair(keys, MoveMsg::Count, "Count"),
+        pair(keys, MoveMsg::Done, "Done"),
+        pair(keys, MoveMsg::Cancel, "Cancel"),
+    ]
+}
+
+// Return the target mode key-command pairs.
+fn target_mode_commands(
+    root: &Node,
+    target_state: &TargetState,
+    keys: &ModeKeys<TargetMsg>,
+) -> Vec<Option<KeyPair>> {
+    let mut pairs = vec![
+        jump(),
+        pair2(keys, TargetMsg::Increment, TargetMsg::Decrement, "Down/Up"),
+    ];
+    if target_state.is_valid(root) {
+        let place_pair = |placement, name| {
+            pair(keys, TargetMsg::Place(placement), name)
+        };
+        pairs.extend([
+            place_pair(Placement::FirstChild, "First Child"),
+            place_pair(Placement::LastChild, "Last Child"),
+            place_pair(Placement::Before, "Before"),
+            place_pair(Placement::After, "After"),
+        ]);
+    }
+    pairs.push(pair(keys, TargetMsg::Cancel, "Cancel"));
+    pairs
+}
+
+// Return the wrap mode key-command pairs.
+fn wrap_mode_commands(keys: &ModeKeys<WrapMsg>) -> Vec<Option<KeyPair>> {
+    vec![
+        pair(keys, WrapMsg::Extend, "More"),
+        pair(keys, WrapMsg::Shrink, "Fewer"),
+        pair(keys, WrapMsg::Confirm, "Confirm"),
+        pair(keys, WrapMsg::Cancel, "Cancel"),
+    ]
+}
+
+// Return the macro mode key-command pairs.
+fn macro_mode_commands(
+    macro_state: &MacroState,
+    keys: &ModeKeys<MacroMsg>,
+) -> Vec<Option<KeyPair>> {
+    let mut pairs = Vec::new();
+    if let MacroState::Play(_) = macro_state {
+        pairs.push(typed("0-9", "Count"));
+    }
+    pairs.push(typed("a-z", "Register"));
+    pairs.push(pair(keys, MacroMsg::Cancel, "Cancel"));
+    pairs
+}
+
+// Return the save mode key-command pairs.
+fn save_mode_commands(keys: &ModeKeys<SaveMsg>) -> Vec<Option<KeyPair>> {
+    vec![
+        pair(keys, SaveMsg::Toggle, "Toggle"),
+        pair(keys, SaveMsg::Confirm, "Confirm"),
+        pair(keys, SaveMsg::Cancel, "Cancel"),
+    ]
+}
+
+// Return the help mode key-command pairs.
+fn help_mode_commands(keys: &ModeKeys<HelpMsg>) -> Vec<Option<KeyPair>> {
+    vec![
+        pair2(keys, HelpMsg::Down, HelpMsg::Up, "Down/Up"),
+        pair(keys, HelpMsg::Close, "Close"),
+    ]
+}
+
+// Convert key-command pairs into a command bar styled by the `theme`.
+fn to_command_bar(pairs: Vec<KeyPair>, theme: &Theme) -> Line<'static> {
     let mut spans = Vec::new();
-    for (key, command) in pairs {
-        spans.push(format!(" {key} ").set_style(style::CMD_KEY));
-        spans.push(format!(" {command}").set_style(style::CMD_NAME));
-        spans.push("    ".into());
+    for KeyPair { keys, name, .. } in pairs {
+        spans.push(format!(" {keys} ").set_style(theme.cmd_key));
+        spans.push(format!(" {name}").set_style(theme.cmd_name));
+        spans.push(SPACER.into());
     }
     spans.pop();  // Remove extra spacer at end
     Line::from(spans)
         .centered()
-        .set_style(style::ACCENT)
-}
-
-/// Return the command bar widget based on the current `model`.
-pub fn command_bar(model: &Model) -> Line {
-    let pairs = match &model.mode {
-        Mode::Confirm(confirm_state) => confirm_mode_commands(confirm_state),
-        Mode::Load(load_state) => load_mode_commands(load_state.size()),
-        Mode::Normal => normal_mode_commands(&model.state.root),
-        Mode::Input(input_state) => input_mode_commands(input_state),
-        Mode::Select(_) => select_mode_commands(model.state.root.size()),
-        Mode::Selected(_) => vec![EDIT, MOVE, DELETE, CANCEL],
-        Mode::Move(_) => vec![DOWN, UP, PROMOTE, DEMOTE, DONE, CANCEL],
-        Mode::Save(_) => vec![TOGGLE, CONFIRM, CANCEL],
+        .set_style(theme.accent)
+}
+
+// Return the key-command pairs shown in the `mode`, with keys from the
+// `keymap`.
+fn command_pairs(
+    state: &SessionState,
+    mode: &Mode,
+    keymap: &Keymap,
+) -> Vec<KeyPair> {
+    let SessionState { root, register, .. } = state;
+    let pairs = match mode {
+        Mode::Confirm(confirm_state) => {
+            confirm_mode_commands(confirm_state, &keymap.confirm)
+        }
+        Mode::Load(load_state) => {
+            load_mode_commands(load_state.size(), &keymap.load)
+        }
+        Mode::Normal => normal_mode_commands(state, &keymap.normal),
+        Mode::Input(input_state) => {
+            input_mode_commands(input_state, &keymap.input)
+        }
+        Mode::Select(_) => select_mode_commands(state, &keymap.select),
+        Mode::Selected(_) => {
+            selected_mode_commands(register, &keymap.selected)
+        }
+        Mode::Move(_) => move_mode_commands(&keymap.move_),
+        Mode::Target(target_state) => {
+            target_mode_commands(root, target_state, &keymap.target)
+        }
+        Mode::Wrap(_) => wrap_mode_commands(&keymap.wrap),
+        Mode::Macro(macro_state) => {
+            macro_mode_commands(macro_state, &keymap.macro_)
+        }
+        Mode::Save(_) => save_mode_commands(&keymap.save),
+        Mode::Help(_) => help_mode_commands(&keymap.help),
     };
-    to_command_bar(pairs)
+    let help = match mode {
+        Mode::Input(_) | Mode::Help(_) => None,
+        _ => pair(&keymap.global, HelpMsg::Open, "Help"),
+    };
+    pairs.into_iter().chain([help]).flatten().collect()
+}
+
+/// Return the key of the command shown at `column` on a command bar of the
+/// given `width` in the `mode`.
+pub fn command_key(
+    state: &SessionState,
+    mode: &Mode,
+    keymap: &Keymap,
+    width: u16,
+    column: u16,
+) -> Option<KeyEvent> {
+    let pairs = command_pairs(state, mode, keymap);
+    let pair_widths: Vec<usize> = pairs.iter()
+        .map(|pair| pair.keys.width() + pair.name.width() + 3)
+        .collect();
+    let total = pair_widths.iter().sum::<usize>()
+        + SPACER.len() * pair_widths.len().saturating_sub(1);
+    // The bar is centered, and cut on both sides if it overflows.
+    let width = width as usize;
+    let mut start = match total <= width {
+        true => ((width - total) / 2) as isize,
+        false => -(((total - width) / 2) as isize),
+    };
+    for (pair, pair_width) in pairs.iter().zip(pair_widths) {
+        let end = start + pair_width as isize;
+        if (start..end).contains(&(column as isize)) {
+            return pair.click;
+        }
+        start = end + SPACER.len() as isize;
+    }
+    None
+}
+
+/// Return the command bar widget based on the current `model`, with keys from
+/// the `keymap` and styled by the `theme`.
+pub fn command_bar(
+    model: &Model,
+    keymap: &Keymap,
+    theme: &Theme,
+) -> Line<'static> {
+    let pairs = command_pairs(&model.state, &model.mode, keymap);
+    to_command_bar(pairs, theme)
 }
 
+#[cfg(test)]
+mod tests {
+    use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
+
+    use crate::{
+        forest::{IdGen, Item, Node},
+        theme::{self, ColorSupport},
+    };
+
+    use super::*;
+
+    #[test]
+    fn click_commands_on_overflowing_bar() {
+        let mut model = Model::default(Node::Empty);
+        let id = IdGen::default().next_id();
+        model.state.root = Node::Empty.prepend(Item::new(id, "a".into()));
+        model.mode = Mode::Select(0);
+        let keymap = Keymap::default();
+        let palette = theme::builtin_palette("dark").unwrap();
+        let theme = Theme::new(palette, ColorSupport::NoColor);
+        let area = Rect::new(0, 0, 40, 1);
+        let mut buffer = Buffer::empty(area);
+        command_bar(&model, &keymap, &theme).render(area, &mut buffer);
+        let text: String = buffer.content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+
+        assert!(command_bar(&model, &keymap, &theme).width() > 40);
+        let pairs = command_pairs(&model.state, &model.mode, &keymap);
+        for pair in &pairs {
+            let name = format!(" {}{SPACER}", pair.name);
+            let Some(start) = text.find(&name) else {
+                continue;
+            };
+            let column = text[..start].width() as u16;
+            let key =
+                command_key(&model.state, &model.mode, &keymap, 40, column);
+            assert_eq!(key, pair.click, "{}", pair.name);
+        }
+    }
+}
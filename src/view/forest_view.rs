@@ -1,22 +1,22 @@
-use ratatui::{
-    text::{Line, Span, Text},
-    widgets::Paragraph,
-};
+use std::ops::Range;
+
+use ratatui::text::{Line, Span, Text};
 
 use crate::{
     forest::{
+        Item,
         Node,
         NodePosition,
         NodeType,
         PreOrderIter,
     },
+    theme::Theme,
     util,
-    view::{
-        style,
-        main_paragraph,
-    },
+    view::Scroll,
 };
 
+const MARK: char = '●';
+
 // Represents a text block used for tree drawing.
 enum IndentBlock {
     Spacer,
@@ -30,25 +30,43 @@ struct ForestIter<'a> {
 }
 
 impl<'a> ForestIter<'a> {
-    fn new(root: &'a Node) -> Self {
+    // Create an iterator over the rows of the forest from the node at
+    // `index`, starting with the indent blocks of its ancestors.
+    fn new(root: &'a Node, index: usize) -> Self {
+        let positions = root.path_positions(index);
+        let mut prefix: Vec<IndentBlock> = positions.iter()
+            .skip(1)
+            .take(positions.len().saturating_sub(2))
+            .map(|position| match position.is_last {
+                true => IndentBlock::Spacer,
+                false => IndentBlock::VertBar,
+            })
+            .collect();
+        // Stand in for the block of the previous sibling, which has a next
+        // sibling and is replaced by the node's own block.
+        if let Some(NodePosition { node_type: NodeType::Sibling, .. }) =
+            positions.last()
+        {
+            prefix.push(IndentBlock::VertBar);
+        }
         ForestIter {
-            prefix: Vec::new(),
-            label_iter: root.iter(),
+            prefix,
+            label_iter: root.iter_from(index),
         }
     }
 }
 
 impl<'a> Iterator for ForestIter<'a> {
-    type Item = (String, &'a str);
+    type Item = (String, &'a Item);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let (label, pos) = self.label_iter.next()?;
+        let (item, pos) = self.label_iter.next()?;
         let NodePosition { node_type, is_last } = pos;
         let mut tree_row = String::new();
         match node_type {
             NodeType::Root => {
                 self.prefix.clear();
-                return Some((tree_row, label));
+                return Some((tree_row, item));
             }
             NodeType::Sibling => {
                 while let Some(IndentBlock::Spacer) = self.prefix.pop() {}
@@ -68,63 +86,192 @@ impl<'a> Iterator for ForestIter<'a> {
             tree_row.push_str("├──");
             self.prefix.push(IndentBlock::VertBar);
         }
-        Some((tree_row, label))
+        Some((tree_row, item))
     }
 }
 
-/// Return the forest widget in normal mode.
-pub fn forest_normal(root: &Node) -> Paragraph {
-    let lines = ForestIter::new(root)
-        .map(|(tree_row, label)| {
-            Line::from(vec![
-                Span::styled(tree_row, style::TREE),
-                Span::raw(label),
-            ])
-        });
-    main_paragraph(Text::from_iter(lines))
+// Return the span of an unhighlighted label, styled if the item is marked.
+fn label_span<'a>(item: &'a Item, theme: &Theme) -> Span<'a> {
+    match item.marked {
+        true => Span::styled(item.label.as_str(), theme.marked),
+        false => Span::raw(item.label.as_str()),
+    }
+}
+
+// Return the formatted index `i` padded to `width`, with a mark if `marked`.
+fn format_index(i: usize, width: usize, marked: bool) -> String {
+    let mark = match marked {
+        true => MARK,
+        false => ' ',
+    };
+    format!(" {i:>width$} {mark} ")
+}
+
+/// Return the forest widget in normal mode, showing the item at `cursor`.
+pub fn forest_normal<'a>(
+    root: &'a Node,
+    cursor: usize,
+    theme: &'a Theme,
+) -> Scroll<'a> {
+    let rows = move |range: Range<usize>| {
+        let lines = ForestIter::new(root, range.start)
+            .zip(range)
+            .map(|((tree_row, item), i)| {
+                let label_span = match i == cursor {
+                    true => {
+                        let label = format!(" {} ", item.label);
+                        Span::styled(label, theme.accent)
+                    }
+                    false => label_span(item, theme),
+                };
+                Line::from(vec![
+                    Span::styled(tree_row, theme.tree),
+                    label_span,
+                ])
+            });
+        Text::from_iter(lines)
+    };
+    Scroll::new(rows, root.size(), cursor, theme)
 }
 
 /// Return the forest widget in select mode.
-pub fn forest_select(root: &Node, current_idx: usize) -> Paragraph {
+pub fn forest_select<'a>(
+    root: &'a Node,
+    current_idx: usize,
+    theme: &'a Theme,
+) -> Scroll<'a> {
     let index_len = util::max_index_length(root.size());
-    let lines = ForestIter::new(root)
-        .enumerate()
-        .map(|(i, (tree_row, label))| {
-            let fmt_index = format!(" {i:>width$}   ", width = index_len);
-            let highlight = i == current_idx;
-            let spans = if highlight {
-                vec![
-                    Span::styled(fmt_index, style::DEFAULT_HL),
-                    Span::styled(tree_row, style::TREE_HL),
-                    Span::styled(format!("{label} "), style::DEFAULT_HL),
-                ]
-            } else {
-                vec![
-                    Span::raw(fmt_index),
-                    Span::styled(tree_row, style::TREE),
-                    Span::raw(label),
-                ]
-            };
-            Line::from(spans)
-        });
-    main_paragraph(Text::from_iter(lines))
+    let rows = move |range: Range<usize>| {
+        let lines = ForestIter::new(root, range.start)
+            .zip(range)
+            .map(|((tree_row, item), i)| {
+                let fmt_index = format_index(i, index_len, item.marked);
+                let highlight = i == current_idx;
+                let spans = if highlight {
+                    vec![
+                        Span::styled(fmt_index, theme.default_hl),
+                        Span::styled(tree_row, theme.tree_hl),
+                        Span::styled(
+                            format!("{} ", item.label),
+                            theme.default_hl,
+                        ),
+                    ]
+                } else {
+                    vec![
+                        Span::raw(fmt_index),
+                        Span::styled(tree_row, theme.tree),
+                        label_span(item, theme),
+                    ]
+                };
+                Line::from(spans)
+            });
+        Text::from_iter(lines)
+    };
+    Scroll::new(rows, root.size(), current_idx, theme)
+}
+
+/// Return the forest widget in target mode, dimming the `sources` subtrees.
+pub fn forest_target<'a>(
+    root: &'a Node,
+    sources: Vec<Range<usize>>,
+    target: usize,
+    theme: &'a Theme,
+) -> Scroll<'a> {
+    let index_len = util::max_index_length(root.size());
+    let rows = move |range: Range<usize>| {
+        let lines = ForestIter::new(root, range.start)
+            .zip(range)
+            .map(|((tree_row, item), i)| {
+                let fmt_index = format_index(i, index_len, item.marked);
+                let spans = if i == target {
+                    vec![
+                        Span::styled(fmt_index, theme.default_hl),
+                        Span::styled(tree_row, theme.tree_hl),
+                        Span::styled(
+                            format!("{} ", item.label),
+                            theme.default_hl,
+                        ),
+                    ]
+                } else if sources.iter().any(|range| range.contains(&i)) {
+                    vec![
+                        Span::raw(fmt_index),
+                        Span::styled(tree_row, theme.tree),
+                        Span::styled(item.label.as_str(), theme.tree),
+                    ]
+                } else {
+                    vec![
+                        Span::raw(fmt_index),
+                        Span::styled(tree_row, theme.tree),
+                        label_span(item, theme),
+                    ]
+                };
+                Line::from(spans)
+            });
+        Text::from_iter(lines)
+    };
+    Scroll::new(rows, root.size(), target, theme)
 }
 
 /// Return the forest widget in selected mode.
-pub fn forest_selected(root: &Node, current_idx: usize) -> Paragraph {
-    let lines = ForestIter::new(root)
-        .enumerate()
-        .map(|(i, (tree_row, label))| {
-            let highlight = i == current_idx;
-            let label_span = match highlight {
-                true => Span::styled(format!(" {label} "), style::DEFAULT_HL),
-                false => Span::raw(label),
-            };
-            Line::from(vec![
-                Span::styled(tree_row, style::TREE),
-                label_span,
-            ])
-        });
-    main_paragraph(Text::from_iter(lines))
+pub fn forest_selected<'a>(
+    root: &'a Node,
+    current_idx: usize,
+    theme: &'a Theme,
+) -> Scroll<'a> {
+    forest_range(root, current_idx..current_idx + 1, theme)
+}
+
+/// Return the forest widget highlighting the labels in the index `range`.
+pub fn forest_range<'a>(
+    root: &'a Node,
+    range: Range<usize>,
+    theme: &'a Theme,
+) -> Scroll<'a> {
+    let index = range.start;
+    let rows = move |rows: Range<usize>| {
+        let lines = ForestIter::new(root, rows.start)
+            .zip(rows)
+            .map(|((tree_row, item), i)| {
+                let highlight = range.contains(&i);
+                let label_span = match highlight {
+                    true => {
+                        let label = format!(" {} ", item.label);
+                        Span::styled(label, theme.default_hl)
+                    }
+                    false => label_span(item, theme),
+                };
+                Line::from(vec![
+                    Span::styled(tree_row, theme.tree),
+                    label_span,
+                ])
+            });
+        Text::from_iter(lines)
+    };
+    Scroll::new(rows, root.size(), index, theme)
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::forest::IdGen;
+
+    use super::*;
+
+    #[test]
+    fn draw_rows_from_any_node() {
+        let mut ids = IdGen::default();
+        let items = [0, 1, 2, 1, 2, 3, 1, 0, 1].into_iter()
+            .enumerate()
+            .map(|(i, depth)| (depth, Item::new(ids.next_id(), i.to_string())));
+        let root = Node::from_outline(items);
+        let rows = |index| -> Vec<String> {
+            ForestIter::new(&root, index)
+                .map(|(tree_row, item)| format!("{tree_row}{}", item.label))
+                .collect()
+        };
+        let all = rows(0);
+
+        for i in 0..=root.size() {
+            assert_eq!(rows(i), all[i..]);
+        }
+    }
+}
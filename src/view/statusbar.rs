@@ -2,36 +2,50 @@ use ratatui::{
     style::{Styled, Stylize},
     text::{Line, Span},
 };
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use crate::{
+    keymap,
     model::{
         ConfirmState,
         FilenameAction,
         FilenameStatus,
         InputState,
         LabelAction,
+        MacroState,
         Mode,
         Model,
         PostSaveAction,
     },
-    view::style
+    theme::Theme,
+    util,
 };
 
 mod confirm {
     pub const NEW: &str = "No saved files, starting new session...";
     pub const DELETE_ITEM: &str = "Delete item?";
+    pub const DELETE_TREE: &str = "Delete item and its descendants?";
+    pub const DELETE_MARKED: &str = "Delete marked items?";
     pub const DELETE_FILE: &str = "Delete file?";
+    pub const CREATE_ITEMS: &str = "Create items from pasted lines?";
 }
 mod input {
     pub const ADD: &str = "Add item";
     pub const EDIT: &str = "Edit item";
+    pub const DUPLICATE_FIND: &str = "Duplicate: prefix/suffix to replace";
+    pub const DUPLICATE_REPLACE: &str = "Duplicate: replace ";
+    pub const DUPLICATE_WITH: &str = " with";
+    pub const WRAP: &str = "Wrap items in new parent";
+    pub const TAG: &str = "Tag marked items";
     pub const RENAME: &str = "Rename file";
     pub const SAVENEW: &str = "Save new file";
+    pub const COMMAND: &str = "Command";
 }
 mod alert {
     pub const EMPTY: &str = "Empty";
     pub const EXISTS: &str = "File Exists";
     pub const INVALID: &str = "Invalid Filename";
+    pub const INVALID_TARGET: &str = "Invalid Target";
 }
 mod post_save {
     pub const LOAD: &str = "Loading";
@@ -41,15 +55,26 @@ const LOAD: &str = "Open a file or start a new session";
 const SELECT: &str = "Selected index: ";
 const SELECTED: &str = "Enter command";
 const MOVE: &str = "Move subtree";
+const COUNT: &str = "Count: ";
+const TARGET: &str = "Move to index: ";
+const WRAP: &str = "Items to wrap: ";
 const SAVE: &str = "Save changes?";
+const HELP: &str = "Keys";
+const RECORD: &str = "Record macro to register: ";
+const PLAY: &str = "Play macro from register: ";
+const RECORDING: &str = "Recording";
 const UNTITLED: &str = "Untitled";
+const MARKED: &str = "Marked";
+const HOISTED: &str = "Hoisted";
+const ELLIPSIS: &str = "…";
+const DIVIDER: &str = " | ";
 
-fn info(text: &str) -> Span {
+fn info(text: &str) -> Span<'_> {
     format!("[{text}]").into()
 }
 
 // Status bar Line with the `message`.
-fn status(text: &str) -> Vec<Span> {
+fn status(text: &str) -> Vec<Span<'_>> {
     vec![text.into()]
 }
 
@@ -61,41 +86,161 @@ fn status_info<'a>(message: &'a str, maybe_info: Option<&'a str>) -> Vec<Span<'a
     }
 }
 
-// Normal mode status bar Line with the filename, if it exists.
-fn status_normal(maybe_filename: Option<&str>) -> Vec<Span> {
-    vec![match maybe_filename {
+// Status bar Line asking for the replacement of `affix` when duplicating.
+fn status_replace<'a>(
+    affix: &'a str,
+    maybe_info: Option<&'a str>,
+) -> Vec<Span<'a>> {
+    let mut spans = vec![
+        input::DUPLICATE_REPLACE.into(),
+        affix.bold(),
+        input::DUPLICATE_WITH.into(),
+    ];
+    if let Some(text) = maybe_info {
+        spans.extend([" | ".into(), info(text)]);
+    }
+    spans
+}
+
+// Normal mode status bar Line with the filename, if it exists, whether the
+// forest `is_hoisted` and the register of the macro being recorded, if any.
+fn status_normal(
+    maybe_filename: Option<&str>,
+    is_hoisted: bool,
+    maybe_register: Option<char>,
+) -> Vec<Span<'_>> {
+    let mut spans = vec![match maybe_filename {
         Some(filename) => filename.bold(),
         None => info(UNTITLED),
-    }]
+    }];
+    if is_hoisted {
+        spans.extend([" | ".into(), info(HOISTED)]);
+    }
+    if let Some(register) = maybe_register {
+        let recording = format!("[{RECORDING} {register}]");
+        spans.extend([" | ".into(), recording.into()]);
+    }
+    spans
 }
 
-// Select mode status bar Line showing the selected `index`.
-fn status_select(index: usize) -> Vec<Span<'static>> {
-    vec![
+// Select mode status bar Line showing the selected `index` and the number of
+// marked items, if any.
+fn status_select(index: usize, mark_count: usize) -> Vec<Span<'static>> {
+    let mut spans = vec![
         SELECT.into(),
         index.to_string().bold(),
-    ]
+    ];
+    if mark_count > 0 {
+        spans.extend([" | ".into(), format!("[{mark_count} {MARKED}]").into()]);
+    }
+    spans
+}
+
+// Append the `count` being typed, if any, to the `content`.
+fn with_count(
+    mut content: Vec<Span<'_>>,
+    maybe_count: Option<usize>,
+) -> Vec<Span<'_>> {
+    if let Some(count) = maybe_count {
+        let count = match count {
+            0 => String::new(),
+            count => count.to_string(),
+        };
+        content.extend([" | ".into(), format!("[{COUNT}{count}]").into()]);
+    }
+    content
+}
+
+// Return the path of `labels` as a breadcrumb fitting in `width`, truncated
+// from the left by whole labels, then by characters.
+fn breadcrumb(labels: &[&str], width: usize) -> String {
+    let mut text = labels.join(util::PATH_SEPARATOR);
+    let mut start = 0;
+    while text.width() > width && start + 1 < labels.len() {
+        start += 1;
+        let rest = labels[start..].join(util::PATH_SEPARATOR);
+        text = format!("{ELLIPSIS}{}{rest}", util::PATH_SEPARATOR);
+    }
+    if text.width() <= width {
+        return text;
+    }
+    let mut kept_width = ELLIPSIS.width();
+    let mut kept: Vec<char> = text.chars()
+        .rev()
+        .take_while(|c| {
+            kept_width += c.width().unwrap_or(0);
+            kept_width <= width
+        })
+        .collect();
+    kept.reverse();
+    format!("{ELLIPSIS}{}", String::from_iter(kept))
+}
+
+// Append the breadcrumb of the path `labels` to the `content`, using the
+// remaining `width` of the status bar.
+fn with_breadcrumb<'a>(
+    mut content: Vec<Span<'a>>,
+    labels: &[&str],
+    width: u16,
+) -> Vec<Span<'a>> {
+    let used: usize = content.iter()
+        .map(Span::width)
+        .sum();
+    let available = (width as usize).saturating_sub(used + DIVIDER.len() + 2);
+    if available > ELLIPSIS.width() {
+        let path = breadcrumb(labels, available);
+        content.extend([DIVIDER.into(), path.into()]);
+    }
+    content
+}
+
+// Target mode status bar Line showing the `target` index and its validity.
+fn status_target(target: usize, is_valid: bool) -> Vec<Span<'static>> {
+    let mut spans = vec![
+        TARGET.into(),
+        target.to_string().bold(),
+    ];
+    if !is_valid {
+        spans.extend([" | ".into(), info(alert::INVALID_TARGET)]);
+    }
+    spans
 }
 
-/// Return the status bar widget based on the `model`.
-pub fn status_bar(model: &Model) -> Line {
+/// Return the status bar widget of `width` based on the `model`, styled by
+/// the `theme`.
+pub fn status_bar<'a>(model: &'a Model, width: u16, theme: &Theme) -> Line<'a> {
     let content = match &model.mode {
         Mode::Confirm(confirm_state) => match confirm_state {
             ConfirmState::NewSession => status(confirm::NEW),
             ConfirmState::DeleteItem(..) => status(confirm::DELETE_ITEM),
+            ConfirmState::DeleteTree(..) => status(confirm::DELETE_TREE),
+            ConfirmState::DeleteMarked => status(confirm::DELETE_MARKED),
             ConfirmState::DeleteFile(_) => status(confirm::DELETE_FILE),
+            ConfirmState::CreateItems(_) => status(confirm::CREATE_ITEMS),
         }
         Mode::Load(_) => status(LOAD),
-        Mode::Normal => status_normal(model.get_filename()),
+        Mode::Normal => {
+            let state = &model.state;
+            let register = state.recording.as_ref()
+                .map(|recording| recording.register);
+            status_normal(model.get_filename(), state.is_hoisted(), register)
+        }
         Mode::Input(InputState::Label(label_state)) => {
-            let message = match label_state.action {
-                LabelAction::Add => input::ADD,
-                LabelAction::Edit(_) => input::EDIT,
-            };
             let info = match label_state.is_empty() {
                 true => Some(alert::EMPTY),
                 false => None,
             };
+            let message = match &label_state.action {
+                LabelAction::Add => input::ADD,
+                LabelAction::Edit(_) => input::EDIT,
+                LabelAction::DuplicateFind(_) => input::DUPLICATE_FIND,
+                LabelAction::Wrap(..) => input::WRAP,
+                LabelAction::Tag(_) => input::TAG,
+                LabelAction::DuplicateReplace(_, affix) => {
+                    let content = status_replace(affix, info);
+                    return status_line(content, theme);
+                }
+            };
             status_info(message, info)
         }
         Mode::Input(InputState::Filename(filename_state)) => {
@@ -111,21 +256,65 @@ pub fn status_bar(model: &Model) -> Line {
             };
             status_info(message, info)
         }
-        Mode::Select(index) => status_select(*index),
+        Mode::Input(InputState::CommandLine(command_line)) => {
+            status_info(input::COMMAND, command_line.info.as_deref())
+        }
+        Mode::Select(index) => {
+            let content = status_select(*index, model.state.root.mark_count());
+            with_count(content, model.state.count)
+        }
         Mode::Selected(_) => status(SELECTED),
-        Mode::Move(_) => status(MOVE),
+        Mode::Move(_) => with_count(status(MOVE), model.state.count),
+        Mode::Target(target_state) => {
+            let is_valid = target_state.is_valid(&model.state.root);
+            status_target(target_state.target, is_valid)
+        }
+        Mode::Wrap(wrap_state) => vec![
+            WRAP.into(),
+            wrap_state.count.to_string().bold(),
+        ],
+        Mode::Macro(MacroState::Record) => status(RECORD),
+        Mode::Macro(MacroState::Play(count)) => {
+            with_count(status(PLAY), Some(*count).filter(|&count| count > 0))
+        }
         Mode::Save(save_state) => {
             let info = match save_state.post_save {
-                PostSaveAction::Load => post_save::LOAD,
-                PostSaveAction::Quit => post_save::QUIT,
+                PostSaveAction::Load => Some(post_save::LOAD),
+                PostSaveAction::Quit => Some(post_save::QUIT),
+                PostSaveAction::Continue => None,
             };
-            status_info(SAVE, Some(info))
+            status_info(SAVE, info)
+        }
+        Mode::Help(help_state) => {
+            let name = keymap::mode_name(&help_state.mode);
+            vec![HELP.into(), " | ".into(), format!("[keys.{name}]").into()]
+        }
+    };
+    let state = &model.state;
+    let labels = match &model.mode {
+        Mode::Normal if state.root.size() > 0 => {
+            state.path_labels(state.cursor)
+        }
+        Mode::Normal => state.hoist_labels(),
+        Mode::Select(index) | Mode::Selected(index) | Mode::Move(index) => {
+            state.path_labels(*index)
         }
+        _ => Vec::new(),
     };
+    match labels.is_empty() {
+        true => status_line(content, theme),
+        false => {
+            status_line(with_breadcrumb(content, &labels, width), theme)
+        }
+    }
+}
+
+// Convert the `content` spans into the styled status bar.
+fn status_line<'a>(content: Vec<Span<'a>>, theme: &Theme) -> Line<'a> {
     let mut spans = vec![" ".into()];
     spans.extend(content);
     Line::from(spans)
     .left_aligned()
-    .set_style(style::ACCENT)
+    .set_style(theme.accent)
 }
 